@@ -38,7 +38,8 @@ fn main() -> ! {
         Config::default().baudrate(115_200.Bd()),
         ((pin16, mux0), (pin7, mux7)),
         clocks,
-    );
+    )
+    .unwrap();
 
     // Create RTC
     let rtc = Rtc::new(dp.HBN);
@@ -75,7 +75,8 @@ fn main() -> ! {
         Config::default().baudrate(2_000_000.Bd()),
         ((pin16, mux0), (pin7, mux7)),
         clocks,
-    );
+    )
+    .unwrap();
 
     // Initialize the led pin to their default state:
     let mut r_led_pin = gpio_pins.pin17.into_pull_down_output();
@@ -2,3 +2,5 @@ PROVIDE(Gpio = DefaultHandler);
 PROVIDE(TimerCh0 = DefaultHandler);
 PROVIDE(TimerCh1 = DefaultHandler);
 PROVIDE(Watchdog = DefaultHandler);
+PROVIDE(Uart0 = DefaultHandler);
+PROVIDE(Uart1 = DefaultHandler);
@@ -20,7 +20,7 @@
 //
 // Currently implemented clock tree configuration options:
 //   - internal 32Mhz RC oscillator for sysclock
-//   - XTAL driving PLL, sysclock frequencies of 48/80/120/160/192Mhz
+//   - XTAL driving PLL, sysclock frequencies of 48/80/120/160Mhz (192Mhz with `overclock`)
 //   - UART using PLL if sysclock is using PLL
 
 use crate::delay::*;
@@ -35,48 +35,205 @@ pub const RC32M: u32 = 32_000_000;
 /// UART peripheral clock frequency when PLL selected
 pub const UART_PLL_FREQ: u32 = 160_000_000;
 
-#[derive(PartialEq, Copy, Clone)]
+/// System clock (fclk/hclk) frequency
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 #[repr(u32)]
 pub enum SysclkFreq {
+    /// Internal 32MHz RC oscillator
     Rc32Mhz = 32_000_000,
+    /// PLL 48MHz tap
     Pll48Mhz = 48_000_000,
+    /// PLL 160MHz tap, divided by 2
+    Pll80Mhz = 80_000_000,
+    /// PLL 120MHz tap
     Pll120Mhz = 120_000_000,
+    /// PLL 160MHz tap
     Pll160Mhz = 160_000_000,
+    /// PLL 192MHz tap
+    ///
+    /// This is out of spec for the BL602, it requires the `overclock` feature.
+    #[cfg(feature = "overclock")]
+    Pll192Mhz = 192_000_000,
+}
+
+/// Register settings deriving a system clock frequency from the PLL
+struct PllTap {
+    /// `reg_pll_sel` value: 0 => 48MHz, 1 => 120MHz, 2 => 160MHz, 3 => 192MHz
+    pll_sel: u8,
+    /// `reg_hclk_div` value, fclk = hclk = tap / (hclk_div + 1)
+    hclk_div: u8,
+    /// `reg_bclk_div` value, bclk = hclk / (bclk_div + 1)
+    bclk_div: u8,
+    /// Internal ROM needs 2 clocks per access above 120MHz
+    irom_2t_access: bool,
+}
+
+impl SysclkFreq {
+    /// PLL tap and dividers for this frequency, `None` for the RC oscillator
+    fn pll_tap(self) -> Option<PllTap> {
+        // recommended: fclk<=160MHz, bclk<=80MHz
+        let (pll_sel, hclk_div, bclk_div, irom_2t_access) = match self {
+            SysclkFreq::Rc32Mhz => return None,
+            SysclkFreq::Pll48Mhz => (0, 0, 0, false),
+            SysclkFreq::Pll80Mhz => (2, 1, 0, false),
+            SysclkFreq::Pll120Mhz => (1, 0, 1, false),
+            SysclkFreq::Pll160Mhz => (2, 0, 1, true),
+            #[cfg(feature = "overclock")]
+            SysclkFreq::Pll192Mhz => (3, 0, 2, true),
+        };
+
+        Some(PllTap {
+            pll_sel,
+            hclk_div,
+            bclk_div,
+            irom_2t_access,
+        })
+    }
+
+    /// Bus clock (bclk) frequency resulting from this system clock
+    fn bclk(self) -> u32 {
+        let bclk_div = self.pll_tap().map(|tap| tap.bclk_div).unwrap_or(0);
+
+        self as u32 / (bclk_div as u32 + 1)
+    }
+}
+
+/// Clock configuration error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClockError {
+    /// The requested system clock needs the PLL, but no XTAL frequency was given to `use_pll`
+    PllRequired(SysclkFreq),
+    /// The XTAL frequency given to `use_pll` can't be used as PLL reference
+    UnsupportedXtal(u32),
+    /// The external crystal did not report ready in time
+    XtalTimeout,
+    /// The requested UART clock can't be divided from the UART source clock
+    UnreachableUartClk { requested: u32, source: u32 },
+    /// The requested SPI clock can't be divided from the bus clock
+    UnreachableSpiClk { requested: u32, source: u32 },
+    /// The requested I2C clock can't be divided from the bus clock
+    UnreachableI2cClk { requested: u32, source: u32 },
+    /// The UART clock divider given to `ClockConfig` is outside of `1..=7`
+    InvalidUartClkDiv(u32),
+    /// The SPI clock divider given to `ClockConfig` is outside of `1..=32`
+    InvalidSpiClkDiv(u32),
+    /// The I2C clock divider given to `ClockConfig` is outside of `1..=255`
+    InvalidI2cClkDiv(u32),
+    /// A driver passed to `reconfigure` can't derive its frequency from the new clocks
+    UnreachableDriverClk { requested: u32, source: u32 },
+    /// A driver passed to `reconfigure` couldn't finish its transfers, the clocks are unchanged
+    DriverBusy,
 }
 
 /// Frozen clock frequencies
 ///
-/// The existance of this value indicates that the clock configuration can no longer be changed
+/// The existance of this value indicates that the clock configuration can no longer be changed,
+/// except through `Strict::reconfigure` or `Precise::reconfigure` which consume it together
+/// with the drivers depending on it.
 #[derive(Clone, Copy)]
 pub struct Clocks {
     sysclk: Hertz,
+    bclk: Hertz,
     uart_clk: Hertz,
     spi_clk: Hertz,
     i2c_clk: Hertz,
-    _xtal_freq: Option<Hertz>,
+    xtal_freq: Option<Hertz>,
     pll_enable: bool,
+    uart_clk_ppm: i32,
+    spi_clk_ppm: i32,
+    i2c_clk_ppm: i32,
 }
 
 impl Clocks {
     pub fn new() -> Self {
         Clocks {
             sysclk: Hertz(RC32M),
+            bclk: Hertz(RC32M),
             uart_clk: Hertz(RC32M),
             spi_clk: Hertz(RC32M),
             i2c_clk: Hertz(RC32M),
-            _xtal_freq: None,
+            xtal_freq: None,
             pll_enable: false,
+            uart_clk_ppm: 0,
+            spi_clk_ppm: 0,
+            i2c_clk_ppm: 0,
         }
     }
 
+    /// Reconstructs the clock frequencies from the current register state
+    ///
+    /// Use this instead of a configurator when the clock tree has already been set up,
+    /// e.g. by a bootloader which chain-loads the application. Nothing is written.
+    ///
+    /// The crystal frequency is derived from the PLL settings. Returns `None` if the system
+    /// clock runs from the crystal while the PLL is off, as its frequency can't be told then.
+    pub fn from_hardware() -> Option<Self> {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        let sysclk = calculate_sys_clock()?;
+        let bus_clock = calculate_bus_clock(sysclk);
+        let xtal_freq = detect_xtal_freq();
+        let pll_enable = pll_is_enabled();
+
+        // hbn_uart_clk_sel: 0 => fclk, 1 => PLL 160MHz
+        let uart_pll = unsafe { &*pac::HBN::ptr() }
+            .hbn_glb
+            .read()
+            .hbn_uart_clk_sel()
+            .bit_is_set();
+        let uart_clk_src = if uart_pll { UART_PLL_FREQ.Hz() } else { sysclk };
+
+        let uart_clk_div = glb.clk_cfg2.read().uart_clk_div().bits() as u32 + 1;
+        let spi_clk_div = glb.clk_cfg3.read().spi_clk_div().bits() as u32 + 1;
+        let i2c_clk_div = glb.clk_cfg3.read().i2c_clk_div().bits() as u32 + 1;
+
+        Some(Clocks {
+            sysclk,
+            bclk: bus_clock,
+            uart_clk: uart_clk_src / uart_clk_div,
+            spi_clk: bus_clock / spi_clk_div,
+            i2c_clk: bus_clock / i2c_clk_div,
+            xtal_freq: xtal_freq.map(Hertz),
+            pll_enable,
+            uart_clk_ppm: 0,
+            spi_clk_ppm: 0,
+            i2c_clk_ppm: 0,
+        })
+    }
+
     pub fn sysclk(&self) -> Hertz {
         self.sysclk
     }
 
+    /// AHB bus clock (hclk), equal to the system clock
+    pub const fn hclk(&self) -> Hertz {
+        self.sysclk
+    }
+
+    /// APB peripheral bus clock (bclk), source of the SPI and I2C clocks
+    pub const fn bclk(&self) -> Hertz {
+        self.bclk
+    }
+
+    /// External crystal frequency, if the PLL is driven by it
+    pub const fn xtal(&self) -> Option<Hertz> {
+        self.xtal_freq
+    }
+
     pub fn pll_enable(&self) -> bool {
         self.pll_enable
     }
 
+    /// PLL output tap frequencies, if the PLL is enabled
+    pub const fn pll_outputs(&self) -> Option<PllOutputs> {
+        if self.pll_enable {
+            Some(PllOutputs::new())
+        } else {
+            None
+        }
+    }
+
     pub const fn uart_clk(&self) -> Hertz {
         self.uart_clk
     }
@@ -88,6 +245,30 @@ impl Clocks {
     pub const fn i2c_clk(&self) -> Hertz {
         self.i2c_clk
     }
+
+    /// Error of the UART clock relative to the requested frequency, in ppm
+    pub const fn uart_clk_ppm(&self) -> i32 {
+        self.uart_clk_ppm
+    }
+
+    /// Error of the SPI clock relative to the requested frequency, in ppm
+    pub const fn spi_clk_ppm(&self) -> i32 {
+        self.spi_clk_ppm
+    }
+
+    /// Error of the I2C clock relative to the requested frequency, in ppm
+    pub const fn i2c_clk_ppm(&self) -> i32 {
+        self.i2c_clk_ppm
+    }
+
+    /// Fills in the ppm errors of the peripheral clocks against the requested frequencies
+    fn with_targets(mut self, uart_clk: u32, spi_clk: u32, i2c_clk: u32) -> Self {
+        self.uart_clk_ppm = error_ppm(self.uart_clk.0, uart_clk);
+        self.spi_clk_ppm = error_ppm(self.spi_clk.0, spi_clk);
+        self.i2c_clk_ppm = error_ppm(self.i2c_clk.0, i2c_clk);
+
+        self
+    }
 }
 
 impl Default for Clocks {
@@ -96,6 +277,208 @@ impl Default for Clocks {
     }
 }
 
+/// Peripherals with a clock gate in the GLB `cgen_cfg1` register
+///
+/// The value is the bit index of the gate in `cgen_cfg1`, which is also the index
+/// of the peripheral's software reset in `swrst_cfg1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Peripheral {
+    /// ADC and DAC (GPIP)
+    Adc = 2,
+    /// Security engine
+    SecEng = 4,
+    /// DMA controller
+    Dma = 12,
+    /// UART0
+    Uart0 = 16,
+    /// UART1
+    Uart1 = 17,
+    /// SPI
+    Spi = 18,
+    /// I2C
+    I2c = 19,
+    /// PWM
+    Pwm = 20,
+    /// Timers and watchdog
+    Timer = 21,
+    /// IR remote
+    Ir = 22,
+    /// Checksum engine
+    Cks = 23,
+}
+
+impl Peripheral {
+    const fn mask(self) -> u32 {
+        1 << self as u8
+    }
+}
+
+impl ClkCfg {
+    /// Enables the clock of `peripheral`
+    pub fn enable_clock(&mut self, peripheral: Peripheral) {
+        set_peripheral_clock(peripheral, true);
+    }
+
+    /// Gates the clock of `peripheral`, the peripheral stops working and draws less current
+    pub fn disable_clock(&mut self, peripheral: Peripheral) {
+        set_peripheral_clock(peripheral, false);
+    }
+
+    /// Checks whether the clock of `peripheral` is enabled
+    pub fn is_clock_enabled(&self, peripheral: Peripheral) -> bool {
+        unsafe { &*pac::GLB::ptr() }.cgen_cfg1.read().bits() & peripheral.mask() != 0
+    }
+
+    /// Pulses the software reset of `peripheral`, bringing its registers back to
+    /// their reset values
+    ///
+    /// The drivers offer a `reset()` that also re-applies their configuration
+    /// afterwards, prefer it when the peripheral is owned by a driver.
+    pub fn reset(&mut self, peripheral: Peripheral) {
+        reset_peripheral(peripheral);
+    }
+}
+
+/// Pulses the software reset of `peripheral` in `swrst_cfg1`
+pub(crate) fn reset_peripheral(peripheral: Peripheral) {
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    // Same sequence as GLB_AHB_Slave1_Reset: low, high, low
+    glb.swrst_cfg1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !peripheral.mask()) });
+    glb.swrst_cfg1
+        .modify(|r, w| unsafe { w.bits(r.bits() | peripheral.mask()) });
+    glb.swrst_cfg1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !peripheral.mask()) });
+}
+
+/// Enables or gates the clock of `peripheral`
+///
+/// Used by the drivers to toggle their own clock gate on construction and release.
+/// The UART, SPI and I2C function clocks are switched as well; the UART function
+/// clock is shared and only turned off when both UARTs are gated.
+pub(crate) fn set_peripheral_clock(peripheral: Peripheral, enable: bool) {
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    // A set bit lets the clock pass, a cleared bit gates it
+    glb.cgen_cfg1.modify(|r, w| unsafe {
+        w.bits(if enable {
+            r.bits() | peripheral.mask()
+        } else {
+            r.bits() & !peripheral.mask()
+        })
+    });
+
+    match peripheral {
+        Peripheral::Uart0 | Peripheral::Uart1 => {
+            let uarts = Peripheral::Uart0.mask() | Peripheral::Uart1.mask();
+            let any_uart = glb.cgen_cfg1.read().bits() & uarts != 0;
+
+            glb.clk_cfg2.modify(|_, w| w.uart_clk_en().bit(any_uart));
+        }
+        Peripheral::Spi => glb.clk_cfg3.modify(|_, w| w.spi_clk_en().bit(enable)),
+        Peripheral::I2c => glb.clk_cfg3.modify(|_, w| w.i2c_clk_en().bit(enable)),
+        _ => {}
+    }
+}
+
+/// Driver whose dividers are derived from the frozen `Clocks`
+///
+/// Implemented by the HAL drivers so they can follow a clock tree reconfiguration.
+/// `Strict::reconfigure` and `Precise::reconfigure` take the drivers which were created
+/// from the previous `Clocks`, usually as a tuple of mutable references. They are suspended
+/// while the clock tree changes and get their dividers recomputed from the returned `Clocks`
+/// afterwards.
+///
+/// Every driver is checked against the new `Clocks` first, nothing is written if one of
+/// them can't reach its frequency. If the clock tree fails to switch, the drivers are resumed
+/// with the previous `Clocks`; the clock tree may have been partially reconfigured.
+pub trait ClockDependent {
+    /// Checks that the driver's frequencies can be derived from `clocks`
+    fn check(&self, _clocks: &Clocks) -> Result<(), ClockError> {
+        Ok(())
+    }
+
+    /// Finish ongoing transfers and stop the peripheral before the clock tree changes
+    ///
+    /// Returns `ClockError::DriverBusy` if the transfers don't finish in a bounded time.
+    fn suspend(&mut self) -> Result<(), ClockError>;
+
+    /// Recompute the dividers from `clocks` and restart the peripheral
+    ///
+    /// If suspending a group of drivers fails, all of them are resumed with the current
+    /// clocks, including those which weren't suspended.
+    fn resume(&mut self, clocks: &Clocks);
+}
+
+impl<T: ClockDependent + ?Sized> ClockDependent for &mut T {
+    fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+        (**self).check(clocks)
+    }
+
+    fn suspend(&mut self) -> Result<(), ClockError> {
+        (**self).suspend()
+    }
+
+    fn resume(&mut self, clocks: &Clocks) {
+        (**self).resume(clocks)
+    }
+}
+
+macro_rules! impl_clock_dependent_tuple {
+    ($($T: ident: $i: tt),+) => {
+        impl<$($T: ClockDependent),+> ClockDependent for ($($T,)+) {
+            fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+                $( self.$i.check(clocks)?; )+
+
+                Ok(())
+            }
+
+            fn suspend(&mut self) -> Result<(), ClockError> {
+                $( self.$i.suspend()?; )+
+
+                Ok(())
+            }
+
+            fn resume(&mut self, clocks: &Clocks) {
+                $( self.$i.resume(clocks); )+
+            }
+        }
+    };
+}
+
+impl_clock_dependent_tuple!(A: 0);
+impl_clock_dependent_tuple!(A: 0, B: 1);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2, D: 3);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6);
+impl_clock_dependent_tuple!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5, G: 6, H: 7);
+
+/// Switches the clock tree to the planned configuration with `dependents` suspended
+///
+/// Shared by `Strict::reconfigure` and `Precise::reconfigure`.
+fn reconfigure<D: ClockDependent>(
+    plan: Result<(ClockConfig, Clocks), ClockError>,
+    clocks: Clocks,
+    _clk_cfg: &mut ClkCfg,
+    mut dependents: D,
+) -> Result<Clocks, ClockError> {
+    let (config, new_clocks) = plan?;
+    dependents.check(&new_clocks)?;
+
+    if let Err(error) = dependents.suspend() {
+        dependents.resume(&clocks);
+        return Err(error);
+    }
+    let result = config.apply().map(|()| new_clocks);
+    dependents.resume(result.as_ref().unwrap_or(&clocks));
+
+    result
+}
+
 /// Strict clock configurator
 ///
 /// This configurator only accepts strictly accurate value. If all available frequency
@@ -168,7 +551,8 @@ impl Strict {
     /// Set the system clock frequency (fclk/hclk)
     ///
     /// Supported frequencies:
-    ///   `32_000_000`, `48_000_000`, `80_000_000`, `120_000_000`, `160_000_000`
+    ///   `32_000_000`, `48_000_000`, `80_000_000`, `120_000_000`, `160_000_000`,
+    ///   and `192_000_000` with the `overclock` feature
     pub fn sys_clk(mut self, freq: SysclkFreq) -> Self {
         self.sysclk = freq;
 
@@ -181,27 +565,48 @@ impl Strict {
     /// Be aware that Rust's panic is sometimes not obvious on embedded devices; if your
     /// program didn't execute as expected, or the `pc` is pointing to somewhere weird
     /// (usually `abort: j abort`), it's likely that this function have panicked.
-    /// Breakpoint on `rust_begin_unwind` may help debugging.
+    /// Breakpoint on `rust_begin_unwind` may help debugging. Use `try_freeze` to handle
+    /// the error instead.
     ///
     /// # Panics
     ///
     /// If strictly accurate value of given `ck_sys` etc. is not reachable, this function
     /// panics.
-    pub fn freeze(self, _clk_cfg: &mut ClkCfg) -> Clocks {
-        // Default to not using the PLL, and selecting the internal RC oscillator if nothing selected
-        let pll_xtal_freq = self.pll_xtal_freq.unwrap_or(0);
-        let pll_enabled = pll_xtal_freq != 0;
-        let sysclk = self.sysclk;
+    pub fn freeze(self, clk_cfg: &mut ClkCfg) -> Clocks {
+        match self.try_freeze(clk_cfg) {
+            Ok(clocks) => clocks,
+            Err(e) => panic!("clock configuration failed: {:?}", e),
+        }
+    }
 
-        // If sysclk isn't 32Mhz but PLL isn't enabled, panic
-        assert!(pll_enabled || sysclk == SysclkFreq::Rc32Mhz);
+    /// Calculate and balance clock registers to configure into the given clock value.
+    ///
+    /// Returns a [`ClockError`] naming the clock that could not be configured instead of
+    /// panicking. All dividers are checked before any register is written.
+    pub fn try_freeze(self, _clk_cfg: &mut ClkCfg) -> Result<Clocks, ClockError> {
+        let (config, clocks) = self.plan()?;
+        config.apply()?;
 
-        // If PLL is available we'll be using the PLL_160Mhz clock, otherwise sysclk
-        let uart_clk_src = if pll_enabled {
-            UART_PLL_FREQ
-        } else {
-            sysclk as u32
-        };
+        Ok(clocks)
+    }
+
+    /// Reconfigure the clock tree after `clocks` have been frozen, see [`ClockDependent`]
+    pub fn reconfigure<D: ClockDependent>(
+        self,
+        clocks: Clocks,
+        clk_cfg: &mut ClkCfg,
+        dependents: D,
+    ) -> Result<Clocks, ClockError> {
+        reconfigure(self.plan(), clocks, clk_cfg, dependents)
+    }
+
+    /// Computes the dividers and the resulting clocks without writing anything
+    fn plan(&self) -> Result<(ClockConfig, Clocks), ClockError> {
+        let pll_xtal_freq = self.pll_xtal_freq.unwrap_or(0);
+        let sysclk = self.sysclk;
+        let pll_enabled = check_sysclk(sysclk, pll_xtal_freq)?;
+        let uart_clk_src = uart_clk_source(sysclk, pll_enabled);
+        let bus_clock = sysclk.bclk();
 
         // UART config
         let uart_clk = self
@@ -212,36 +617,16 @@ impl Strict {
         let uart_clk_div = {
             let ans = uart_clk_src / uart_clk;
 
-            if !(1..=7).contains(&ans) || ans * uart_clk != uart_clk_src {
-                panic!("unreachable uart_clk")
+            if !(1..=UART_CLK_DIV_MAX).contains(&ans) || ans * uart_clk != uart_clk_src {
+                return Err(ClockError::UnreachableUartClk {
+                    requested: uart_clk,
+                    source: uart_clk_src,
+                });
             }
 
-            ans as u8
+            ans
         };
 
-        // Enable system clock, PLL + crystal if required
-        // omit if settings match boot defaults
-        if sysclk != SysclkFreq::Pll160Mhz || pll_xtal_freq != 40_000_000 {
-            match sysclk {
-                SysclkFreq::Rc32Mhz => glb_set_system_clk_rc32(),
-                _ => glb_set_system_clk_pll(sysclk as u32, pll_xtal_freq),
-            };
-        }
-
-        // If PLL is enabled, use that for the UART base clock
-        // Otherwise, use sysclk as the UART clock
-        unsafe { &*pac::HBN::ptr() }
-            .hbn_glb
-            .modify(|_, w| w.hbn_uart_clk_sel().bit(pll_enabled));
-
-        // Write UART clock divider
-        unsafe { &*pac::GLB::ptr() }.clk_cfg2.modify(|_, w| unsafe {
-            w.uart_clk_div()
-                .bits(uart_clk_div - 1_u8)
-                .uart_clk_en()
-                .set_bit()
-        });
-
         // SPI config
         let spi_clk = self
             .target_spi_clk
@@ -249,19 +634,15 @@ impl Strict {
             .unwrap_or(32_000_000u32);
 
         // SPI Clock Divider (BUS_CLK/(N+1)), default BUS_CLK/4
-        let bus_clock = calculate_bus_clock();
-        let spi_clk_div = bus_clock.0 / spi_clk;
+        let spi_clk_div = bus_clock / spi_clk;
 
-        if spi_clk_div == 0 || spi_clk_div > 0b100000 {
-            panic!("Unreachable SPI_CLK");
+        if spi_clk_div == 0 || spi_clk_div > SPI_CLK_DIV_MAX {
+            return Err(ClockError::UnreachableSpiClk {
+                requested: spi_clk,
+                source: bus_clock,
+            });
         }
 
-        let spi_clk_div = ((spi_clk_div - 1) & 0b11111) as u8;
-        // Write SPI clock divider
-        unsafe { &*pac::GLB::ptr() }
-            .clk_cfg3
-            .modify(|_, w| unsafe { w.spi_clk_en().set_bit().spi_clk_div().bits(spi_clk_div) });
-
         // I2C config
         let i2c_clk = self
             .target_i2c_clk
@@ -269,26 +650,24 @@ impl Strict {
             .unwrap_or(32_000_000u32);
 
         // I2C Clock Divider (BUS_CLK/(N+1)), default BUS_CLK/255
-        let i2c_clk_div = bus_clock.0 / i2c_clk;
+        let i2c_clk_div = bus_clock / i2c_clk;
 
-        if i2c_clk_div == 0 || i2c_clk_div > 255 {
-            panic!("Unreachable I2C_CLK");
+        if i2c_clk_div == 0 || i2c_clk_div > I2C_CLK_DIV_MAX {
+            return Err(ClockError::UnreachableI2cClk {
+                requested: i2c_clk,
+                source: bus_clock,
+            });
         }
 
-        let i2c_clk_div = ((i2c_clk_div - 1) & 0xff) as u8;
-        // Write I2C clock divider
-        unsafe { &*pac::GLB::ptr() }
-            .clk_cfg3
-            .modify(|_, w| unsafe { w.i2c_clk_en().set_bit().i2c_clk_div().bits(i2c_clk_div) });
+        let config = ClockConfig::new(sysclk, self.pll_xtal_freq)?
+            .uart_clk_div(uart_clk_div)?
+            .spi_clk_div(spi_clk_div)?
+            .i2c_clk_div(i2c_clk_div)?;
 
-        Clocks {
-            sysclk: Hertz(sysclk as u32),
-            uart_clk: Hertz(uart_clk),
-            spi_clk: Hertz(spi_clk),
-            i2c_clk: Hertz(i2c_clk),
-            _xtal_freq: Some(Hertz(pll_xtal_freq)),
-            pll_enable: pll_enabled,
-        }
+        Ok((
+            config,
+            config.clocks().with_targets(uart_clk, spi_clk, i2c_clk),
+        ))
     }
 }
 
@@ -298,8 +677,382 @@ impl Default for Strict {
     }
 }
 
-/// Gets the current bus clock rate
-fn calculate_bus_clock() -> Hertz {
+/// Precise clock configurator
+///
+/// This configurator selects the dividers which give the smallest frequency error for
+/// each requested peripheral clock. Unlike `Strict`, an inexact request never fails;
+/// the frequencies actually configured and their error in ppm are reported by the
+/// returned `Clocks`.
+///
+/// Requests beyond the range of a divider are clamped to the closest reachable value.
+pub struct Precise {
+    target_i2c_clk: Option<NonZeroU32>,
+    target_spi_clk: Option<NonZeroU32>,
+    target_uart_clk: Option<NonZeroU32>,
+    pll_xtal_freq: Option<u32>,
+    sysclk: SysclkFreq,
+}
+
+impl Precise {
+    /// Create a precise configurator
+    pub fn new() -> Self {
+        Precise {
+            target_i2c_clk: None,
+            target_spi_clk: None,
+            target_uart_clk: None,
+            pll_xtal_freq: None,
+            sysclk: SysclkFreq::Rc32Mhz,
+        }
+    }
+
+    /// Sets the desired frequency for the I2C-CLK clock
+    pub fn i2c_clk(mut self, freq: impl Into<Hertz>) -> Self {
+        let freq_hz = freq.into().0;
+
+        self.target_i2c_clk = NonZeroU32::new(freq_hz);
+
+        self
+    }
+
+    /// Sets the desired frequency for the SPI-CLK clock
+    pub fn spi_clk(mut self, freq: impl Into<Hertz>) -> Self {
+        let freq_hz = freq.into().0;
+
+        self.target_spi_clk = NonZeroU32::new(freq_hz);
+
+        self
+    }
+
+    /// Sets the desired frequency for the UART-CLK clock
+    pub fn uart_clk(mut self, freq: impl Into<Hertz>) -> Self {
+        let freq_hz = freq.into().0;
+
+        self.target_uart_clk = NonZeroU32::new(freq_hz);
+
+        self
+    }
+
+    /// Enables PLL clock source, using external XTAL frequency provided
+    pub fn use_pll(mut self, freq: impl Into<Hertz>) -> Self {
+        self.pll_xtal_freq = Some(freq.into().0);
+
+        self
+    }
+
+    /// Set the system clock frequency (fclk/hclk)
+    pub fn sys_clk(mut self, freq: SysclkFreq) -> Self {
+        self.sysclk = freq;
+
+        self
+    }
+
+    /// Calculate and configure the dividers closest to the requested clock values.
+    ///
+    /// # Panics
+    ///
+    /// If the system clock can't be configured (see `try_freeze`), this function panics.
+    pub fn freeze(self, clk_cfg: &mut ClkCfg) -> Clocks {
+        match self.try_freeze(clk_cfg) {
+            Ok(clocks) => clocks,
+            Err(e) => panic!("clock configuration failed: {:?}", e),
+        }
+    }
+
+    /// Calculate and configure the dividers closest to the requested clock values.
+    ///
+    /// Only system clock errors (missing PLL, unsupported XTAL, XTAL power-on timeout)
+    /// are reported; peripheral clocks always resolve to their nearest value.
+    pub fn try_freeze(self, _clk_cfg: &mut ClkCfg) -> Result<Clocks, ClockError> {
+        let (config, clocks) = self.plan()?;
+        config.apply()?;
+
+        Ok(clocks)
+    }
+
+    /// Reconfigure the clock tree after `clocks` have been frozen, see [`ClockDependent`]
+    pub fn reconfigure<D: ClockDependent>(
+        self,
+        clocks: Clocks,
+        clk_cfg: &mut ClkCfg,
+        dependents: D,
+    ) -> Result<Clocks, ClockError> {
+        reconfigure(self.plan(), clocks, clk_cfg, dependents)
+    }
+
+    /// Computes the dividers and the resulting clocks without writing anything
+    fn plan(&self) -> Result<(ClockConfig, Clocks), ClockError> {
+        let pll_xtal_freq = self.pll_xtal_freq.unwrap_or(0);
+        let sysclk = self.sysclk;
+        let pll_enabled = check_sysclk(sysclk, pll_xtal_freq)?;
+        let uart_clk_src = uart_clk_source(sysclk, pll_enabled);
+        let bus_clock = sysclk.bclk();
+
+        let uart_target = self
+            .target_uart_clk
+            .map(|f| f.get())
+            .unwrap_or(uart_clk_src);
+        let spi_target = self
+            .target_spi_clk
+            .map(|f| f.get())
+            .unwrap_or(32_000_000u32);
+        let i2c_target = self
+            .target_i2c_clk
+            .map(|f| f.get())
+            .unwrap_or(32_000_000u32);
+
+        let config = ClockConfig::new(sysclk, self.pll_xtal_freq)?
+            .uart_clk_div(nearest_clk_div(uart_clk_src, uart_target, UART_CLK_DIV_MAX))?
+            .spi_clk_div(nearest_clk_div(bus_clock, spi_target, SPI_CLK_DIV_MAX))?
+            .i2c_clk_div(nearest_clk_div(bus_clock, i2c_target, I2C_CLK_DIV_MAX))?;
+
+        Ok((
+            config,
+            config
+                .clocks()
+                .with_targets(uart_target, spi_target, i2c_target),
+        ))
+    }
+}
+
+impl Default for Precise {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Divider settings of the clock tree
+///
+/// This is what the configurators derive from the requested frequencies. `clocks`
+/// computes the resulting frequencies without touching any register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockConfig {
+    sysclk: SysclkFreq,
+    pll_xtal_freq: Option<u32>,
+    uart_clk_div: u32,
+    spi_clk_div: u32,
+    i2c_clk_div: u32,
+}
+
+impl ClockConfig {
+    /// Creates a configuration running from `sysclk`, all peripheral clock dividers are 1
+    ///
+    /// `pll_xtal_freq` is the XTAL frequency driving the PLL, `None` if the PLL is not used.
+    pub fn new(sysclk: SysclkFreq, pll_xtal_freq: Option<u32>) -> Result<Self, ClockError> {
+        let pll_enabled = check_sysclk(sysclk, pll_xtal_freq.unwrap_or(0))?;
+
+        Ok(ClockConfig {
+            sysclk,
+            pll_xtal_freq: pll_xtal_freq.filter(|_| pll_enabled),
+            uart_clk_div: 1,
+            spi_clk_div: 1,
+            i2c_clk_div: 1,
+        })
+    }
+
+    /// Sets the UART clock divider, `1..=7`
+    pub fn uart_clk_div(mut self, div: u32) -> Result<Self, ClockError> {
+        if !(1..=UART_CLK_DIV_MAX).contains(&div) {
+            return Err(ClockError::InvalidUartClkDiv(div));
+        }
+        self.uart_clk_div = div;
+
+        Ok(self)
+    }
+
+    /// Sets the SPI clock divider, `1..=32`
+    pub fn spi_clk_div(mut self, div: u32) -> Result<Self, ClockError> {
+        if !(1..=SPI_CLK_DIV_MAX).contains(&div) {
+            return Err(ClockError::InvalidSpiClkDiv(div));
+        }
+        self.spi_clk_div = div;
+
+        Ok(self)
+    }
+
+    /// Sets the I2C clock divider, `1..=255`
+    pub fn i2c_clk_div(mut self, div: u32) -> Result<Self, ClockError> {
+        if !(1..=I2C_CLK_DIV_MAX).contains(&div) {
+            return Err(ClockError::InvalidI2cClkDiv(div));
+        }
+        self.i2c_clk_div = div;
+
+        Ok(self)
+    }
+
+    /// Computes the frequencies of the whole clock tree for this configuration
+    pub fn clocks(&self) -> Clocks {
+        let pll_enable = self.pll_xtal_freq.is_some();
+        let sysclk = self.sysclk as u32;
+        let bclk = self.sysclk.bclk();
+        let uart_clk_src = uart_clk_source(self.sysclk, pll_enable);
+
+        Clocks {
+            sysclk: Hertz(sysclk),
+            bclk: Hertz(bclk),
+            uart_clk: Hertz(uart_clk_src / self.uart_clk_div),
+            spi_clk: Hertz(bclk / self.spi_clk_div),
+            i2c_clk: Hertz(bclk / self.i2c_clk_div),
+            xtal_freq: self.pll_xtal_freq.map(Hertz),
+            pll_enable,
+            uart_clk_ppm: 0,
+            spi_clk_ppm: 0,
+            i2c_clk_ppm: 0,
+        }
+    }
+
+    /// Writes the configuration into the clock registers
+    fn apply(&self) -> Result<(), ClockError> {
+        let pll_xtal_freq = self.pll_xtal_freq.unwrap_or(0);
+
+        set_sysclk(self.sysclk, pll_xtal_freq)?;
+        set_uart_clk_div(self.uart_clk_div, self.pll_xtal_freq.is_some());
+        set_spi_clk_div(self.spi_clk_div);
+        set_i2c_clk_div(self.i2c_clk_div);
+
+        Ok(())
+    }
+}
+
+/// Frequencies of the PLL output taps
+///
+/// The PLL runs at 480MHz, all taps are enabled when the PLL is powered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PllOutputs {
+    pub pll_480m: Hertz,
+    pub pll_240m: Hertz,
+    pub pll_192m: Hertz,
+    pub pll_160m: Hertz,
+    pub pll_120m: Hertz,
+    pub pll_96m: Hertz,
+    pub pll_80m: Hertz,
+    pub pll_48m: Hertz,
+    pub pll_32m: Hertz,
+}
+
+impl PllOutputs {
+    const fn new() -> Self {
+        PllOutputs {
+            pll_480m: Hertz(480_000_000),
+            pll_240m: Hertz(240_000_000),
+            pll_192m: Hertz(192_000_000),
+            pll_160m: Hertz(160_000_000),
+            pll_120m: Hertz(120_000_000),
+            pll_96m: Hertz(96_000_000),
+            pll_80m: Hertz(80_000_000),
+            pll_48m: Hertz(48_000_000),
+            pll_32m: Hertz(32_000_000),
+        }
+    }
+}
+
+/// Largest UART clock divider accepted by the configurators
+const UART_CLK_DIV_MAX: u32 = 7;
+/// Largest SPI clock divider (`spi_clk_div` is 5 bits wide)
+const SPI_CLK_DIV_MAX: u32 = 32;
+/// Largest I2C clock divider accepted by the configurators
+const I2C_CLK_DIV_MAX: u32 = 255;
+
+/// Checks that the requested system clock can be reached, returns whether the PLL is used
+fn check_sysclk(sysclk: SysclkFreq, pll_xtal_freq: u32) -> Result<bool, ClockError> {
+    // Default to not using the PLL, and selecting the internal RC oscillator if nothing selected
+    let pll_enabled = pll_xtal_freq != 0;
+
+    // If sysclk isn't 32Mhz but PLL isn't enabled, bail out
+    if !pll_enabled && sysclk != SysclkFreq::Rc32Mhz {
+        return Err(ClockError::PllRequired(sysclk));
+    }
+
+    if pll_enabled {
+        PllParams::for_xtal(pll_xtal_freq)?;
+    }
+
+    Ok(pll_enabled)
+}
+
+/// If PLL is available we'll be using the PLL_160Mhz clock, otherwise sysclk
+fn uart_clk_source(sysclk: SysclkFreq, pll_enabled: bool) -> u32 {
+    if pll_enabled {
+        UART_PLL_FREQ
+    } else {
+        sysclk as u32
+    }
+}
+
+/// Picks the divider in `1..=max_div` giving the frequency closest to `target`
+fn nearest_clk_div(source: u32, target: u32, max_div: u32) -> u32 {
+    let lower = (source / target).clamp(1, max_div);
+    let upper = (lower + 1).min(max_div);
+
+    let error = |div: u32| (source / div).abs_diff(target);
+
+    if error(upper) < error(lower) {
+        upper
+    } else {
+        lower
+    }
+}
+
+/// Relative error of `actual` against `target` in parts per million
+fn error_ppm(actual: u32, target: u32) -> i32 {
+    let ppm = (actual as i64 - target as i64) * 1_000_000 / target as i64;
+
+    ppm.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Enable system clock, PLL + crystal if required
+fn set_sysclk(sysclk: SysclkFreq, pll_xtal_freq: u32) -> Result<(), ClockError> {
+    // omit if settings match boot defaults and the clock tree is still configured that way
+    if sysclk != SysclkFreq::Pll160Mhz
+        || pll_xtal_freq != 40_000_000
+        || system_core_clock_get() != SysclkFreq::Pll160Mhz as u32
+    {
+        match sysclk {
+            SysclkFreq::Rc32Mhz => glb_set_system_clk_rc32(),
+            _ => glb_set_system_clk_pll(sysclk, pll_xtal_freq)?,
+        };
+    } else if let Some(tap) = sysclk.pll_tap() {
+        // The PLL is left running, but the bootloader may have picked other dividers;
+        // write ours so bclk matches what `ClockConfig::clocks` reports
+        glb_set_system_clk_div(tap.hclk_div, tap.bclk_div);
+    }
+
+    Ok(())
+}
+
+/// Selects the UART source clock and writes the UART clock divider
+fn set_uart_clk_div(div: u32, pll_enabled: bool) {
+    // If PLL is enabled, use that for the UART base clock
+    // Otherwise, use sysclk as the UART clock
+    unsafe { &*pac::HBN::ptr() }
+        .hbn_glb
+        .modify(|_, w| w.hbn_uart_clk_sel().bit(pll_enabled));
+
+    // The clock gate is left to the drivers, see `set_peripheral_clock`
+    unsafe { &*pac::GLB::ptr() }
+        .clk_cfg2
+        .modify(|_, w| unsafe { w.uart_clk_div().bits((div - 1) as u8) });
+}
+
+/// Writes the SPI clock divider (BUS_CLK/(N+1))
+fn set_spi_clk_div(div: u32) {
+    let div = ((div - 1) & 0b11111) as u8;
+
+    unsafe { &*pac::GLB::ptr() }
+        .clk_cfg3
+        .modify(|_, w| unsafe { w.spi_clk_div().bits(div) });
+}
+
+/// Writes the I2C clock divider (BUS_CLK/(N+1))
+fn set_i2c_clk_div(div: u32) {
+    let div = ((div - 1) & 0xff) as u8;
+
+    unsafe { &*pac::GLB::ptr() }
+        .clk_cfg3
+        .modify(|_, w| unsafe { w.i2c_clk_div().bits(div) });
+}
+
+/// Gets the current system clock (fclk/hclk) rate, `None` if it runs from an unknown crystal
+fn calculate_sys_clock() -> Option<Hertz> {
     let root_clk_sel = unsafe { &*pac::GLB::ptr() }
         .clk_cfg0
         .read()
@@ -315,15 +1068,10 @@ fn calculate_bus_clock() -> Hertz {
         .read()
         .reg_hclk_div()
         .bits();
-    let bclk_div = unsafe { &*pac::GLB::ptr() }
-        .clk_cfg0
-        .read()
-        .reg_bclk_div()
-        .bits();
 
     let root = match root_clk_sel {
-        0 => 32_000_000_u32.Hz(),
-        1 => 40_000_000_u32.Hz(),
+        0 => RC32M.Hz(),
+        1 => detect_xtal_freq()?.Hz(),
         _ => match pll_clk_sel {
             0 => 48_000_000_u32.Hz(),
             1 => 120_000_000_u32.Hz(),
@@ -332,7 +1080,60 @@ fn calculate_bus_clock() -> Hertz {
         },
     };
 
-    root / (hclk_div as u32 + 1) / (bclk_div as u32 + 1)
+    Some(root / (hclk_div as u32 + 1))
+}
+
+/// Gets the current bus clock rate from the system clock rate
+fn calculate_bus_clock(sysclk: Hertz) -> Hertz {
+    let bclk_div = unsafe { &*pac::GLB::ptr() }
+        .clk_cfg0
+        .read()
+        .reg_bclk_div()
+        .bits();
+
+    sysclk / (bclk_div as u32 + 1)
+}
+
+/// Checks whether the PLL is powered up and enabled as a clock source
+fn pll_is_enabled() -> bool {
+    let pll_powered = unsafe { &*pac::PDS::ptr() }
+        .pu_rst_clkpll
+        .read()
+        .pu_clkpll()
+        .bit_is_set();
+    let pll_enabled = unsafe { &*pac::GLB::ptr() }
+        .clk_cfg0
+        .read()
+        .reg_pll_en()
+        .bit_is_set();
+
+    pll_powered && pll_enabled
+}
+
+/// Recovers the XTAL frequency from the PLL fractional divider
+///
+/// The PLL runs at 480MHz with `clkpll_sdmin` = 480MHz / XTAL in 6.18 fixed point,
+/// the result is rounded to the closest supported XTAL frequency.
+fn detect_xtal_freq() -> Option<u32> {
+    if !pll_is_enabled() {
+        return None;
+    }
+
+    let sdmin = unsafe { &*pac::PDS::ptr() }
+        .clkpll_sdm
+        .read()
+        .clkpll_sdmin()
+        .bits();
+    if sdmin == 0 {
+        return None;
+    }
+
+    let freq = ((480_000_000_u64 << 18) / sdmin as u64) as u32;
+
+    [24_000_000, 26_000_000, 32_000_000, 38_400_000, 40_000_000]
+        .iter()
+        .copied()
+        .min_by_key(|xtal: &u32| xtal.abs_diff(freq))
 }
 
 /// Sets the system clock in the (undocumented) system_core_clock register
@@ -376,199 +1177,250 @@ fn glb_set_system_clk_div(hclkdiv: u8, bclkdiv: u8) {
     delay.delay_us(1);
 }
 
-// This is a reference implementation of `PDS_Select_XTAL_As_PLL_Ref`.
-// It is currently unused.
-#[allow(dead_code)]
-fn pds_select_xtal_as_pll_ref() {
-    unsafe { &*pac::PDS::ptr() }.clkpll_top_ctrl.modify(|_, w| {
-        w.clkpll_refclk_sel()
-            .set_bit()
-            .clkpll_xtal_rc32m_sel()
-            .clear_bit()
-    });
-}
-
-// This is a reference implementation of `PDS_Power_Off_PLL`.
-// It is currently unused.
-#[allow(dead_code)]
-fn pds_power_off_pll() {
-    unsafe { &*pac::PDS::ptr() }
-        .pu_rst_clkpll
-        .modify(|_, w| w.pu_clkpll_sfreg().clear_bit().pu_clkpll().clear_bit());
-
-    unsafe { &*pac::PDS::ptr() }.pu_rst_clkpll.modify(|_, w| {
-        w.clkpll_pu_cp()
-            .clear_bit()
-            .clkpll_pu_pfd()
-            .clear_bit()
-            .clkpll_pu_fbdv()
-            .clear_bit()
-            .clkpll_pu_postdiv()
-            .clear_bit()
-    });
-}
-
-/// Power up PLL using C function built into on-chip ROM
 // Powering on the PLL means powering *OFF* the PLL if it's already running.
-// If we're running from flash, this causes a lockup. Running from RAM or ROM is
-// okay though. So there's probably some steps we need to take to do this safely,
-// but the flash peripheral is not documented yet.
-// The easiest solution is to use the C function built into the ROM to do the change.
-#[inline]
-fn pds_power_on_pll_rom(freq: u32) {
-    // Lookup table for ROM function addresses is at 0x21010800
-    // index in the table for RomDriver_PDS_Power_On_PLL is 88
-    // each entry is a pointer, size of each entry is 4 bytes (sizeof usize)
-    // we can can use core::ptr::offset to calculate the true offset for us.
-    // In my ROM, the address of rom_pds_power_on_pll is 0x2101_4ACE,
-    // this line is commented out in case this is not true for others
-    let rom_function_table_base = (0x2101_0800) as *mut usize;
-    let power_on_pll_entry = unsafe { rom_function_table_base.offset(88) };
-    let power_on_pll_addr = unsafe { power_on_pll_entry.read_volatile() };
-    //assert_eq!(power_on_pll_addr, 0x2101_4ACE);
-
-    let romdriver_pds_power_on_pll = unsafe {
-        core::mem::transmute::<*const (), extern "C" fn(usize) -> usize>(
-            power_on_pll_addr as *const (),
-        )
-    };
-    let xtal_src = match freq {
-        24_000_000 => 1,
-        32_000_000 => 2,
-        38_400_000 => 3,
-        40_000_000 => 4,
-        26_000_000 => 5,
-        _ => panic!("Unsupported PLL clock source"),
-    };
-
-    // 0 == success, 1 == failure, 2 == timeout
-    let pll_success = romdriver_pds_power_on_pll(xtal_src);
-    assert_eq!(pll_success, 0);
+// The flash controller is clocked from a PLL tap, so XIP fetches stall until the PLL
+// is locked again. The power-on sequence below (`PDS_Power_On_PLL` in the vendor SDK)
+// is therefore linked into `.data`, which riscv-rt copies into ITCM at startup, and
+// must not touch flash while it runs: no calls into `.text`, no `.rodata` lookups and
+// no interrupts. Registers are accessed through inline assembly with the addresses
+// and field positions of the vendor `pds_reg.h`, since the PAC accessors are not
+// guaranteed to be inlined.
+
+/// PDS peripheral base address
+const PDS_BASE: usize = 0x4000_E000;
+
+const PDS_PU_RST_CLKPLL: usize = PDS_BASE + 0x400;
+const PDS_CLKPLL_TOP_CTRL: usize = PDS_BASE + 0x404;
+const PDS_CLKPLL_CP: usize = PDS_BASE + 0x408;
+const PDS_CLKPLL_RZ: usize = PDS_BASE + 0x40C;
+const PDS_CLKPLL_FBDV: usize = PDS_BASE + 0x410;
+const PDS_CLKPLL_SDM: usize = PDS_BASE + 0x418;
+const PDS_CLKPLL_OUTPUT_EN: usize = PDS_BASE + 0x41C;
+
+// PDS_PU_RST_CLKPLL fields
+const CLKPLL_SDM_RESET: u32 = 1 << 0;
+const CLKPLL_RESET_FBDV: u32 = 1 << 2;
+const CLKPLL_PU_POSTDIV: u32 = 1 << 4;
+const CLKPLL_PU_FBDV: u32 = 1 << 5;
+const CLKPLL_PU_PFD: u32 = 1 << 7;
+const CLKPLL_PU_CP: u32 = 1 << 8;
+const PU_CLKPLL_SFREG: u32 = 1 << 9;
+const PU_CLKPLL: u32 = 1 << 10;
+
+// PDS_CLKPLL_TOP_CTRL fields
+const CLKPLL_POSTDIV_POS: u32 = 0;
+const CLKPLL_POSTDIV_MSK: u32 = 0x7F << CLKPLL_POSTDIV_POS;
+const CLKPLL_REFDIV_RATIO_POS: u32 = 8;
+const CLKPLL_REFDIV_RATIO_MSK: u32 = 0xF << CLKPLL_REFDIV_RATIO_POS;
+const CLKPLL_XTAL_RC32M_SEL: u32 = 1 << 12;
+const CLKPLL_REFCLK_SEL: u32 = 1 << 16;
+
+// PDS_CLKPLL_CP fields
+const CLKPLL_ICP_5U_POS: u32 = 4;
+const CLKPLL_ICP_1U_POS: u32 = 6;
+const CLKPLL_INT_FRAC_SW: u32 = 1 << 8;
+const CLKPLL_CP_MSK: u32 =
+    (0x3 << CLKPLL_ICP_5U_POS) | (0x3 << CLKPLL_ICP_1U_POS) | CLKPLL_INT_FRAC_SW;
+
+// PDS_CLKPLL_RZ fields
+const CLKPLL_R4_SHORT: u32 = 1 << 8;
+const CLKPLL_C3_POS: u32 = 12;
+const CLKPLL_CZ_POS: u32 = 14;
+const CLKPLL_RZ_POS: u32 = 16;
+const CLKPLL_RZ_MSK: u32 =
+    CLKPLL_R4_SHORT | (0x3 << CLKPLL_C3_POS) | (0x3 << CLKPLL_CZ_POS) | (0x7 << CLKPLL_RZ_POS);
+
+// PDS_CLKPLL_FBDV fields
+const CLKPLL_SEL_SAMPLE_CLK_POS: u32 = 0;
+const CLKPLL_SEL_FB_CLK_POS: u32 = 2;
+const CLKPLL_FBDV_MSK: u32 = (0x3 << CLKPLL_SEL_SAMPLE_CLK_POS) | (0x3 << CLKPLL_SEL_FB_CLK_POS);
+
+// PDS_CLKPLL_SDM fields
+const CLKPLL_SDMIN_MSK: u32 = 0xFF_FFFF;
+
+/// Enables all nine PLL output taps
+const CLKPLL_OUTPUT_EN_ALL: u32 = 0x1FF;
+
+/// Pre-computed PLL register values for one XTAL frequency
+///
+/// Each value is already shifted into place so that the power-on sequence
+/// doesn't need any lookup while flash is unavailable.
+#[derive(Clone, Copy)]
+struct PllParams {
+    cp: u32,
+    rz: u32,
+    sdmin: u32,
 }
 
-/// Minimal implementation of power-on pll. Currently only allows external xtal
-/// Fails when running from flash - use the pds_power_on_pll_rom for now
-// TODO: work out how to safely power off PLL while running from flash
-//
-// This is a reference implementation of `PDS_Power_On_PLL`.
-// It is currently unused.
-#[allow(dead_code)]
-fn pds_power_on_pll(freq: u32) {
-    let pds = unsafe { &*pac::PDS::ptr() };
-    let mut delay = McycleDelay::new(system_core_clock_get());
-
-    pds_select_xtal_as_pll_ref();
-
-    // power off PLL first - this step is required
-    pds_power_off_pll();
-
-    // PLL param config
-    if freq == 26_000_000 {
-        pds.clkpll_cp.modify(|_, w| unsafe {
-            w.clkpll_icp_1u()
-                .bits(1)
-                .clkpll_icp_5u()
-                .bits(0)
-                .clkpll_int_frac_sw()
-                .set_bit()
-        });
-
-        pds.clkpll_rz.modify(|_, w| unsafe {
-            w.clkpll_c3()
-                .bits(2)
-                .clkpll_cz()
-                .bits(2)
-                .clkpll_rz()
-                .bits(5)
-                .clkpll_r4_short()
-                .clear_bit()
-        });
-    } else {
-        pds.clkpll_cp.modify(|_, w| unsafe {
-            w.clkpll_icp_1u()
-                .bits(0)
-                .clkpll_icp_5u()
-                .bits(2)
-                .clkpll_int_frac_sw()
-                .clear_bit()
-        });
-
-        pds.clkpll_rz.modify(|_, w| unsafe {
-            w.clkpll_c3()
-                .bits(3)
-                .clkpll_cz()
-                .bits(1)
-                .clkpll_rz()
-                .bits(1)
-                .clkpll_r4_short()
-                .set_bit()
-        });
-    }
-
-    pds.clkpll_top_ctrl
-        .modify(|_, w| unsafe { w.clkpll_postdiv().bits(0x14).clkpll_refdiv_ratio().bits(2) });
-
-    pds.clkpll_sdm.modify(|_, w| unsafe {
-        w.clkpll_sdmin().bits(match freq {
+impl PllParams {
+    /// Loop filter and fractional divider values for the supported XTAL frequencies
+    ///
+    /// This is a plain table lookup, no register is read.
+    fn for_xtal(freq: u32) -> Result<Self, ClockError> {
+        let sdmin = match freq {
             24_000_000 => 0x50_0000,
             32_000_000 => 0x3C_0000,
             38_400_000 => 0x32_0000,
             40_000_000 => 0x30_0000,
             26_000_000 => 0x49_D39D,
-            _ => panic!(),
-        })
-    });
+            _ => return Err(ClockError::UnsupportedXtal(freq)),
+        };
 
-    pds.clkpll_fbdv.modify(|_, w| unsafe {
-        w.clkpll_sel_fb_clk()
-            .bits(1)
-            .clkpll_sel_sample_clk()
-            .bits(1)
-    });
+        // 26MHz isn't an integer fraction of 480MHz and needs the fractional mode
+        let (icp_1u, icp_5u, int_frac_sw, c3, cz, rz, r4_short) = if freq == 26_000_000 {
+            (1, 0, true, 2, 2, 5, false)
+        } else {
+            (0, 2, false, 3, 1, 1, true)
+        };
 
-    /*************************/
-    /* PLL power up sequence */
-    /*************************/
-    pds.pu_rst_clkpll
-        .modify(|_, w| w.pu_clkpll_sfreg().set_bit());
+        let mut cp = (icp_1u << CLKPLL_ICP_1U_POS) | (icp_5u << CLKPLL_ICP_5U_POS);
+        if int_frac_sw {
+            cp |= CLKPLL_INT_FRAC_SW;
+        }
 
-    delay.delay_us(5);
+        let mut rz = (c3 << CLKPLL_C3_POS) | (cz << CLKPLL_CZ_POS) | (rz << CLKPLL_RZ_POS);
+        if r4_short {
+            rz |= CLKPLL_R4_SHORT;
+        }
 
-    pds.pu_rst_clkpll.modify(|_, w| w.pu_clkpll().set_bit());
+        Ok(PllParams { cp, rz, sdmin })
+    }
+}
 
-    pds.pu_rst_clkpll.modify(|_, w| {
-        w.clkpll_pu_cp()
-            .set_bit()
-            .clkpll_pu_pfd()
-            .set_bit()
-            .clkpll_pu_fbdv()
-            .set_bit()
-            .clkpll_pu_postdiv()
-            .set_bit()
-    });
+#[cfg(target_arch = "riscv32")]
+#[inline(always)]
+unsafe fn ram_read(addr: usize) -> u32 {
+    let value: u32;
+    core::arch::asm!("lw {0}, 0({1})", out(reg) value, in(reg) addr, options(nostack));
+    value
+}
 
-    delay.delay_us(5);
+#[cfg(target_arch = "riscv32")]
+#[inline(always)]
+unsafe fn ram_write(addr: usize, value: u32) {
+    core::arch::asm!("sw {0}, 0({1})", in(reg) value, in(reg) addr, options(nostack));
+}
 
-    pds.pu_rst_clkpll
-        .modify(|_, w| w.clkpll_sdm_reset().set_bit());
+#[cfg(target_arch = "riscv32")]
+#[inline(always)]
+unsafe fn ram_modify(addr: usize, mask: u32, value: u32) {
+    ram_write(addr, (ram_read(addr) & !mask) | value);
+}
 
-    delay.delay_us(1);
+#[cfg(target_arch = "riscv32")]
+#[inline(always)]
+unsafe fn ram_delay_us(us: u32, cycles_per_us: u32) {
+    let cycles = us.wrapping_mul(cycles_per_us);
+    let start: u32;
+    core::arch::asm!("csrr {0}, mcycle", out(reg) start, options(nomem, nostack));
+
+    loop {
+        let now: u32;
+        core::arch::asm!("csrr {0}, mcycle", out(reg) now, options(nomem, nostack));
+        if now.wrapping_sub(start) > cycles {
+            break;
+        }
+    }
+}
 
-    pds.pu_rst_clkpll
-        .modify(|_, w| w.clkpll_reset_fbdv().set_bit());
+/// Power up PLL using the external XTAL as reference, see `PDS_Power_On_PLL`
+///
+/// # Safety
+///
+/// Must be called with interrupts disabled; runs from ITCM while flash is stalled.
+/// `us` is the number of core clock cycles per microsecond.
+#[cfg(target_arch = "riscv32")]
+#[inline(never)]
+#[link_section = ".data.bl602_hal.pds_power_on_pll"]
+unsafe fn pds_power_on_pll_ram(params: PllParams, us: u32) {
+    // Select XTAL as PLL reference (PDS_Select_XTAL_As_PLL_Ref)
+    ram_modify(
+        PDS_CLKPLL_TOP_CTRL,
+        CLKPLL_REFCLK_SEL | CLKPLL_XTAL_RC32M_SEL,
+        CLKPLL_REFCLK_SEL,
+    );
+
+    // Power off PLL first - this step is required (PDS_Power_Off_PLL)
+    ram_modify(PDS_PU_RST_CLKPLL, PU_CLKPLL_SFREG | PU_CLKPLL, 0);
+    ram_modify(
+        PDS_PU_RST_CLKPLL,
+        CLKPLL_PU_CP | CLKPLL_PU_PFD | CLKPLL_PU_FBDV | CLKPLL_PU_POSTDIV,
+        0,
+    );
 
-    delay.delay_us(2);
+    // PLL param config
+    ram_modify(PDS_CLKPLL_CP, CLKPLL_CP_MSK, params.cp);
+    ram_modify(PDS_CLKPLL_RZ, CLKPLL_RZ_MSK, params.rz);
+    ram_modify(
+        PDS_CLKPLL_TOP_CTRL,
+        CLKPLL_POSTDIV_MSK | CLKPLL_REFDIV_RATIO_MSK,
+        (0x14 << CLKPLL_POSTDIV_POS) | (2 << CLKPLL_REFDIV_RATIO_POS),
+    );
+    ram_modify(PDS_CLKPLL_SDM, CLKPLL_SDMIN_MSK, params.sdmin);
+    ram_modify(
+        PDS_CLKPLL_FBDV,
+        CLKPLL_FBDV_MSK,
+        (1 << CLKPLL_SEL_FB_CLK_POS) | (1 << CLKPLL_SEL_SAMPLE_CLK_POS),
+    );
+
+    // PLL power up sequence
+    ram_modify(PDS_PU_RST_CLKPLL, PU_CLKPLL_SFREG, PU_CLKPLL_SFREG);
+    ram_delay_us(5, us);
+
+    ram_modify(PDS_PU_RST_CLKPLL, PU_CLKPLL, PU_CLKPLL);
+    ram_modify(
+        PDS_PU_RST_CLKPLL,
+        CLKPLL_PU_CP | CLKPLL_PU_PFD | CLKPLL_PU_FBDV | CLKPLL_PU_POSTDIV,
+        CLKPLL_PU_CP | CLKPLL_PU_PFD | CLKPLL_PU_FBDV | CLKPLL_PU_POSTDIV,
+    );
+    ram_delay_us(5, us);
+
+    ram_modify(PDS_PU_RST_CLKPLL, CLKPLL_SDM_RESET, CLKPLL_SDM_RESET);
+    ram_delay_us(1, us);
+
+    ram_modify(PDS_PU_RST_CLKPLL, CLKPLL_RESET_FBDV, CLKPLL_RESET_FBDV);
+    ram_delay_us(2, us);
+
+    ram_modify(PDS_PU_RST_CLKPLL, CLKPLL_RESET_FBDV, 0);
+    ram_delay_us(1, us);
+
+    ram_modify(PDS_PU_RST_CLKPLL, CLKPLL_SDM_RESET, 0);
+
+    // Wait for the PLL to settle and enable all PLL clock outputs
+    // before returning to code running from flash
+    ram_delay_us(55, us);
+    ram_modify(
+        PDS_CLKPLL_OUTPUT_EN,
+        CLKPLL_OUTPUT_EN_ALL,
+        CLKPLL_OUTPUT_EN_ALL,
+    );
+}
 
-    pds.pu_rst_clkpll
-        .modify(|_, w| w.clkpll_reset_fbdv().clear_bit());
+// The register sequence only exists on the chip, host builds (unit tests) never get here
+#[cfg(not(target_arch = "riscv32"))]
+unsafe fn pds_power_on_pll_ram(_params: PllParams, _us: u32) {
+    unreachable!("the PLL can only be powered on on the BL602")
+}
 
-    delay.delay_us(1);
+/// Power up PLL and enable all PLL clock outputs. Currently only allows external xtal
+fn pds_power_on_pll(freq: u32) -> Result<(), ClockError> {
+    let params = PllParams::for_xtal(freq)?;
+    // Read before flash stalls, the delays in RAM can't look anything up
+    let cycles_per_us = system_core_clock_get() / 1_000_000;
+
+    let mie = riscv::register::mstatus::read().mie();
+    unsafe {
+        riscv::interrupt::disable();
+        pds_power_on_pll_ram(params, cycles_per_us);
+        if mie {
+            riscv::interrupt::enable();
+        }
+    }
 
-    pds.pu_rst_clkpll
-        .modify(|_, w| w.clkpll_sdm_reset().clear_bit());
+    Ok(())
 }
 
-fn aon_power_on_xtal() -> Result<(), &'static str> {
+fn aon_power_on_xtal() -> Result<(), ClockError> {
     unsafe { &*pac::AON::ptr() }
         .rf_top_aon
         .modify(|_, w| w.pu_xtal_aon().set_bit().pu_xtal_buf_aon().set_bit());
@@ -590,7 +1442,7 @@ fn aon_power_on_xtal() -> Result<(), &'static str> {
     }
 
     if timeout == 120 {
-        Err("timeout occured")
+        Err(ClockError::XtalTimeout)
     } else {
         Ok(())
     }
@@ -609,12 +1461,6 @@ fn hbn_set_root_clk_sel_rc32() {
         .modify(|_, w| unsafe { w.hbn_root_clk_sel().bits(0b00u8) });
 }
 
-fn pds_enable_pll_all_clks() {
-    unsafe { &*pac::PDS::ptr() }
-        .clkpll_output_en
-        .modify(|r, w| unsafe { w.bits(r.bits() | 0x1FF) });
-}
-
 /// Sets the system clock to use the internal 32Mhz RC oscillator
 fn glb_set_system_clk_rc32() {
     // reg_bclk_en = reg_hclk_en = reg_fclk_en = 1, cannot be zero
@@ -632,6 +1478,11 @@ fn glb_set_system_clk_rc32() {
 
     glb_set_system_clk_div(0, 0);
 
+    // 1 clock internal rom access is enough at 32MHz
+    unsafe { &*pac::L1C::ptr() }
+        .l1c_config
+        .modify(|_, w| w.irom_2t_access().clear_bit());
+
     // Update sysclock
     system_core_clock_set(RC32M);
 
@@ -642,20 +1493,21 @@ fn glb_set_system_clk_rc32() {
 }
 
 /// Sets the system clock to use the PLL with external crystal
-fn glb_set_system_clk_pll(target_core_clk: u32, xtal_freq: u32) {
+fn glb_set_system_clk_pll(sysclk: SysclkFreq, xtal_freq: u32) -> Result<(), ClockError> {
+    let tap = match sysclk.pll_tap() {
+        Some(tap) => tap,
+        None => return Err(ClockError::PllRequired(sysclk)),
+    };
+    let target_core_clk = sysclk as u32;
+
     // Ensure clock is running off internal RC oscillator before changing anything else
     glb_set_system_clk_rc32();
 
     // Power up the external crystal before we start up the PLL
-    aon_power_on_xtal().unwrap();
+    aon_power_on_xtal()?;
 
     // Power up PLL and enable all PLL clock output
-    pds_power_on_pll_rom(xtal_freq);
-
-    let mut delay = McycleDelay::new(system_core_clock_get());
-    delay.delay_us(55);
-
-    pds_enable_pll_all_clks();
+    pds_power_on_pll(xtal_freq)?;
 
     // Enable PLL
     unsafe { &*pac::GLB::ptr() }
@@ -665,25 +1517,17 @@ fn glb_set_system_clk_pll(target_core_clk: u32, xtal_freq: u32) {
     // select which pll output clock to use before
     // selecting root clock via HBN_Set_ROOT_CLK_Sel
     // Note that 192Mhz is out of spec
-    unsafe { &*pac::GLB::ptr() }.clk_cfg0.modify(|_, w| unsafe {
-        w.reg_pll_sel().bits(match target_core_clk {
-            48_000_000 => 0,
-            120_000_000 => 1,
-            160_000_000 => 2,
-            192_000_000 => 3,
-            _ => {
-                panic!()
-            }
-        })
-    });
+    unsafe { &*pac::GLB::ptr() }
+        .clk_cfg0
+        .modify(|_, w| unsafe { w.reg_pll_sel().bits(tap.pll_sel) });
 
-    // Keep bclk <= 80MHz
-    if target_core_clk > 48_000_000 {
-        glb_set_system_clk_div(0, 1);
+    // Divide down to fclk and keep bclk <= 80MHz
+    if tap.hclk_div != 0 || tap.bclk_div != 0 {
+        glb_set_system_clk_div(tap.hclk_div, tap.bclk_div);
     }
 
     // For frequencies above 120Mhz we need 2 clocks to access internal rom
-    if target_core_clk > 120_000_000 {
+    if tap.irom_2t_access {
         unsafe { &*pac::L1C::ptr() }
             .l1c_config
             .modify(|_, w| w.irom_2t_access().set_bit());
@@ -702,4 +1546,111 @@ fn glb_set_system_clk_pll(target_core_clk: u32, xtal_freq: u32) {
     unsafe { &*pac::GLB::ptr() }
         .swrst_cfg2
         .modify(|_, w| w.pka_clk_sel().set_bit());
+
+    Ok(())
+}
+
+// The tests only cover the register-free parts of the clock tree and run on the host:
+// cargo test --lib --target x86_64-unknown-linux-gnu
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XTAL: Option<u32> = Some(40_000_000);
+
+    fn check_sysclk_tree(sysclk: SysclkFreq, xtal: Option<u32>, bclk: u32, uart_clk: u32) {
+        let clocks = ClockConfig::new(sysclk, xtal).unwrap().clocks();
+
+        assert_eq!(clocks.sysclk(), Hertz(sysclk as u32));
+        assert_eq!(clocks.hclk(), Hertz(sysclk as u32));
+        assert_eq!(clocks.bclk(), Hertz(bclk));
+        assert_eq!(clocks.uart_clk(), Hertz(uart_clk));
+        assert_eq!(clocks.spi_clk(), Hertz(bclk));
+        assert_eq!(clocks.i2c_clk(), Hertz(bclk));
+        assert_eq!(clocks.xtal(), xtal.map(Hertz));
+        assert_eq!(clocks.pll_enable(), xtal.is_some());
+        assert_eq!(clocks.pll_outputs().is_some(), xtal.is_some());
+    }
+
+    #[test]
+    fn clocks_for_each_sysclk() {
+        check_sysclk_tree(SysclkFreq::Rc32Mhz, None, 32_000_000, 32_000_000);
+        check_sysclk_tree(SysclkFreq::Rc32Mhz, XTAL, 32_000_000, UART_PLL_FREQ);
+        check_sysclk_tree(SysclkFreq::Pll48Mhz, XTAL, 48_000_000, UART_PLL_FREQ);
+        check_sysclk_tree(SysclkFreq::Pll80Mhz, XTAL, 80_000_000, UART_PLL_FREQ);
+        check_sysclk_tree(SysclkFreq::Pll120Mhz, XTAL, 60_000_000, UART_PLL_FREQ);
+        check_sysclk_tree(SysclkFreq::Pll160Mhz, XTAL, 80_000_000, UART_PLL_FREQ);
+    }
+
+    #[cfg(feature = "overclock")]
+    #[test]
+    fn clocks_for_overclocked_sysclk() {
+        check_sysclk_tree(SysclkFreq::Pll192Mhz, XTAL, 64_000_000, UART_PLL_FREQ);
+    }
+
+    #[test]
+    fn pll_sysclk_requires_xtal() {
+        assert_eq!(
+            ClockConfig::new(SysclkFreq::Pll160Mhz, None),
+            Err(ClockError::PllRequired(SysclkFreq::Pll160Mhz))
+        );
+        assert_eq!(
+            ClockConfig::new(SysclkFreq::Pll160Mhz, Some(12_000_000)),
+            Err(ClockError::UnsupportedXtal(12_000_000))
+        );
+    }
+
+    #[test]
+    fn clocks_for_each_uart_divider() {
+        let config = ClockConfig::new(SysclkFreq::Pll160Mhz, XTAL).unwrap();
+
+        for div in 1..=UART_CLK_DIV_MAX {
+            let clocks = config.uart_clk_div(div).unwrap().clocks();
+            assert_eq!(clocks.uart_clk(), Hertz(UART_PLL_FREQ / div));
+        }
+    }
+
+    #[test]
+    fn clocks_for_each_spi_divider() {
+        let config = ClockConfig::new(SysclkFreq::Pll160Mhz, XTAL).unwrap();
+
+        for div in 1..=SPI_CLK_DIV_MAX {
+            let clocks = config.spi_clk_div(div).unwrap().clocks();
+            assert_eq!(clocks.spi_clk(), Hertz(80_000_000 / div));
+        }
+    }
+
+    #[test]
+    fn clocks_for_each_i2c_divider() {
+        let config = ClockConfig::new(SysclkFreq::Pll160Mhz, XTAL).unwrap();
+
+        for div in 1..=I2C_CLK_DIV_MAX {
+            let clocks = config.i2c_clk_div(div).unwrap().clocks();
+            assert_eq!(clocks.i2c_clk(), Hertz(80_000_000 / div));
+        }
+    }
+
+    #[test]
+    fn out_of_range_dividers_are_rejected() {
+        let config = ClockConfig::new(SysclkFreq::Rc32Mhz, None).unwrap();
+
+        assert_eq!(
+            config.uart_clk_div(0),
+            Err(ClockError::InvalidUartClkDiv(0))
+        );
+        assert_eq!(
+            config.uart_clk_div(UART_CLK_DIV_MAX + 1),
+            Err(ClockError::InvalidUartClkDiv(UART_CLK_DIV_MAX + 1))
+        );
+        assert_eq!(config.spi_clk_div(0), Err(ClockError::InvalidSpiClkDiv(0)));
+        assert_eq!(
+            config.spi_clk_div(SPI_CLK_DIV_MAX + 1),
+            Err(ClockError::InvalidSpiClkDiv(SPI_CLK_DIV_MAX + 1))
+        );
+        assert_eq!(config.i2c_clk_div(0), Err(ClockError::InvalidI2cClkDiv(0)));
+        assert_eq!(
+            config.i2c_clk_div(I2C_CLK_DIV_MAX + 1),
+            Err(ClockError::InvalidI2cClkDiv(I2C_CLK_DIV_MAX + 1))
+        );
+    }
 }
@@ -1,5 +1,9 @@
 //! General Purpose Input/Output
+use core::cell::Cell;
+use core::convert::Infallible;
 use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU32, Ordering};
+use critical_section::Mutex;
 
 use crate::pac;
 
@@ -32,6 +36,166 @@ pub trait InterruptPin {
     fn disable_interrupt(&mut self);
     fn clear_interrupt_pending_bit(&mut self);
     fn check_interrupt(&self) -> bool;
+    /// Registers `handler` to be called from the HAL's `Gpio` interrupt handler when the pin's
+    /// interrupt is pending, the pending bit is cleared before calling it
+    ///
+    /// While a `wait_for_*` future of the pin is pending the interrupt wakes the future and
+    /// the handler isn't called. The future leaves the interrupt masked, call
+    /// `enable_interrupt` to get the handler called again.
+    fn on_interrupt(&mut self, handler: fn());
+    /// Removes the handler registered with `on_interrupt`
+    fn remove_interrupt_handler(&mut self);
+}
+
+const PIN_COUNT: usize = 23;
+
+// Handlers registered with `InterruptPin::on_interrupt`, one per pin
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_HANDLER: Mutex<Cell<Option<fn()>>> = Mutex::new(Cell::new(None));
+static INTERRUPT_HANDLERS: [Mutex<Cell<Option<fn()>>>; PIN_COUNT] = [NO_HANDLER; PIN_COUNT];
+
+/// Returns the pins with a pending interrupt, bit N is set when pin N is pending
+pub fn pending_interrupts() -> u32 {
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    glb.gpio_int_stat1.read().bits() & ((1 << PIN_COUNT) - 1)
+}
+
+fn set_interrupt_handler(pin: usize, handler: Option<fn()>) {
+    critical_section::with(|cs| INTERRUPT_HANDLERS[pin].borrow(cs).set(handler));
+}
+
+/// Clears the pending interrupts of the pins in `mask`
+fn clear_interrupts(mask: u32) {
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    // A set bit clears the interrupt, it has to be reset afterwards to catch the next one
+    glb.gpio_int_clr1
+        .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    glb.gpio_int_clr1
+        .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+}
+
+/// Calls the handlers registered with `InterruptPin::on_interrupt` for the pending pins
+///
+/// Returns whether the user `Gpio` handler has to run, i.e. no registered handler
+/// was called or some pending pins have none.
+pub(crate) fn dispatch_interrupts() -> bool {
+    let pending = pending_interrupts();
+    #[allow(unused_mut)]
+    let mut handled = 0;
+
+    // A waiting future takes the interrupt of its pin over, the handler isn't called
+    #[cfg(feature = "async")]
+    {
+        handled |= wait::wake_pending(pending);
+    }
+
+    for (pin, handler) in INTERRUPT_HANDLERS.iter().enumerate() {
+        let mask = 1 << pin;
+        if pending & !handled & mask == 0 {
+            continue;
+        }
+        if let Some(handler) = critical_section::with(|cs| handler.borrow(cs).get()) {
+            clear_interrupts(mask);
+            handler();
+            handled |= mask;
+        }
+    }
+
+    handled == 0 || pending & !handled != 0
+}
+
+#[cfg(feature = "async")]
+mod wait {
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use core::task::{Context, Poll};
+
+    use atomic_waker::AtomicWaker;
+
+    use super::{clear_interrupts, PIN_COUNT};
+    use crate::interrupts::{enable_interrupt, Interrupt};
+    use crate::pac;
+
+    #[allow(clippy::declare_interior_mutable_const)]
+    const NO_WAKER: AtomicWaker = AtomicWaker::new();
+    static WAKERS: [AtomicWaker; PIN_COUNT] = [NO_WAKER; PIN_COUNT];
+
+    // Pins with a waiting future, and pins whose interrupt fired since they started waiting
+    static WAITING: AtomicU32 = AtomicU32::new(0);
+    static FIRED: AtomicU32 = AtomicU32::new(0);
+
+    fn mask_interrupts(mask: u32) {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        glb.gpio_int_mask1
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    }
+
+    /// Masks and wakes the waiting pins in `pending`, returns the pins that were handled
+    pub(super) fn wake_pending(pending: u32) -> u32 {
+        let woken = pending & WAITING.load(Ordering::SeqCst);
+        if woken == 0 {
+            return 0;
+        }
+
+        // Level triggers keep firing until masked
+        mask_interrupts(woken);
+        clear_interrupts(woken);
+        FIRED.fetch_or(woken, Ordering::SeqCst);
+
+        for (pin, waker) in WAKERS.iter().enumerate() {
+            if woken & (1 << pin) != 0 {
+                waker.wake();
+            }
+        }
+
+        woken
+    }
+
+    /// Completes when the interrupt of `pin` fires
+    ///
+    /// The pin's trigger has to be configured and its interrupt unmasked right after creating it.
+    pub(super) struct InterruptFuture {
+        mask: u32,
+        pin: usize,
+    }
+
+    impl InterruptFuture {
+        pub(super) fn new(pin: usize) -> Self {
+            let mask = 1 << pin;
+
+            FIRED.fetch_and(!mask, Ordering::SeqCst);
+            WAITING.fetch_or(mask, Ordering::SeqCst);
+            enable_interrupt(Interrupt::Gpio);
+
+            InterruptFuture { mask, pin }
+        }
+    }
+
+    impl Future for InterruptFuture {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            WAKERS[self.pin].register(cx.waker());
+
+            if FIRED.load(Ordering::SeqCst) & self.mask != 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    impl Drop for InterruptFuture {
+        fn drop(&mut self) {
+            mask_interrupts(self.mask);
+            WAITING.fetch_and(!self.mask, Ordering::SeqCst);
+            FIRED.fetch_and(!self.mask, Ordering::SeqCst);
+        }
+    }
 }
 
 pub use uart_sig::*;
@@ -203,11 +367,97 @@ pub struct ClkCfg {
     pub(crate) _ownership: (),
 }
 
+/// Clock sources that can be routed to a pin with `into_clock_out`
+///
+/// The BL602 has no dedicated clock output mux, the clock is generated by the PWM channel
+/// behind the pin (channel = pin number % 5) dividing down the selected source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockOutSource {
+    /// Internal 32MHz RC oscillator, through XCLK
+    ///
+    /// Only available while XCLK runs from the RC oscillator.
+    Rc32m,
+    /// External crystal, through XCLK
+    ///
+    /// Only available while XCLK runs from the crystal. XCLK isn't switched for the clock
+    /// output as it may drive the system clock and other peripherals.
+    Xtal,
+    /// Bus clock, whatever the system clock runs from
+    Bclk,
+    /// 32.768KHz clock (RC32K or XTAL32K)
+    F32k,
+}
+
+/// Clock output error, returned by `into_clock_out`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ClockOutError {
+    /// XCLK runs from the other oscillator, see [`ClockOutSource`]
+    SourceUnavailable(ClockOutSource),
+    /// The requested frequency can't be divided from the source frequency
+    UnreachableFrequency { requested: u32, source: u32 },
+}
+
+mod clock_out {
+    use super::{ClockOutError, ClockOutSource};
+    use crate::clock::{Clocks, RC32M};
+    use crate::pac;
+    use crate::pwm::Waveform;
+
+    /// Computes the PWM channel settings producing `freq` from `source`
+    pub(super) fn waveform(
+        source: ClockOutSource,
+        freq: u32,
+        clocks: &Clocks,
+    ) -> Result<Waveform, ClockOutError> {
+        // reg_clk_sel: 0 => XCLK, 1 => BCLK, 2 => 32K
+        let (clk_sel, source_freq) = match source {
+            ClockOutSource::Rc32m if !xclk_is_xtal() => (0, RC32M),
+            ClockOutSource::Xtal if xclk_is_xtal() => match clocks.xtal() {
+                Some(xtal) => (0, xtal.0),
+                None => return Err(ClockOutError::SourceUnavailable(source)),
+            },
+            ClockOutSource::Rc32m | ClockOutSource::Xtal => {
+                return Err(ClockOutError::SourceUnavailable(source))
+            }
+            ClockOutSource::Bclk => (1, clocks.bclk().0),
+            ClockOutSource::F32k => (2, 32_768),
+        };
+
+        // The output frequency is source / clk_div / period, use the smallest divider
+        // so the period, and with it the duty cycle, is as fine as possible
+        let ratio = (source_freq + freq / 2) / freq.max(1);
+        if freq == 0 || ratio < 2 {
+            return Err(ClockOutError::UnreachableFrequency {
+                requested: freq,
+                source: source_freq,
+            });
+        }
+        let clk_div = ratio.div_ceil(u16::MAX as u32);
+        let period = (ratio + clk_div / 2) / clk_div;
+
+        Ok(Waveform {
+            clk_sel,
+            clk_div: clk_div as u16,
+            period: period as u16,
+            thre1: 0,
+            thre2: (period / 2) as u16,
+        })
+    }
+
+    /// Whether XCLK runs from the crystal rather than from the RC oscillator
+    fn xclk_is_xtal() -> bool {
+        let hbn = unsafe { &*pac::HBN::ptr() };
+
+        // hbn_root_clk_sel: bit 0 selects XCLK (0 => RC32M, 1 => XTAL), bit 1 selects the PLL
+        hbn.hbn_glb.read().hbn_root_clk_sel().bits() & 0b01 != 0
+    }
+}
+
 /*
-// todo: english
-    在GPIO模式下，可以设置内部上下拉，以类型状态机模式设计
-    SPI、UART、I2C等数字功能下，可以设置内部上下拉，但不会影响返回类型的状态
-    ADC、DAC下，软件禁止设置内部上下拉。HAL库不会生成此类函数，以免出错。
+    In GPIO mode the internal pull-up/pull-down can be set, this is part of the type state.
+    In digital functions like SPI, UART and I2C the pulls can be set but don't change the type state.
+    In analog mode (ADC, DAC) pulls must not be set, the HAL doesn't generate such functions to avoid mistakes.
 */
 
 /// Hi-Z Floating pin (type state)
@@ -227,15 +477,95 @@ pub struct Output<MODE> {
     _mode: PhantomData<MODE>,
 }
 
+/// Open-drain output mode (type state)
+///
+/// The pin only ever drives low, a high level releases the line.
+pub struct OpenDrain;
+
+/// Input and output mode switchable at runtime (type state)
+pub struct Flex;
+
 /// UART pin mode (type state)
 pub struct Uart;
 
+/// Clock output pin mode (type state)
+pub struct ClockOut;
+
+/// Pin modes driving the pin, which have a configurable drive strength
+#[doc(hidden)]
+pub trait Driven {}
+
+impl<MODE> Driven for Output<MODE> {}
+impl Driven for Uart {}
+impl Driven for Spi {}
+impl Driven for I2c {}
+impl Driven for ClockOut {}
+impl Driven for OpenDrain {}
+impl Driven for Flex {}
+impl Driven for Sdio {}
+impl Driven for Flash {}
+impl Driven for Pwm {}
+impl Driven for ExtPa {}
+impl Driven for Jtag {}
+
+/// Output driver strength, written to `reg_gpio_N_drv`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveStrength {
+    /// Weakest driver, the reset value
+    Level0 = 0,
+    /// Drive level 1
+    Level1 = 1,
+    /// Drive level 2
+    Level2 = 2,
+    /// Strongest driver
+    Level3 = 3,
+}
+
 /// SPI pin mode (type state)
 pub struct Spi;
 
 /// I2C pin mode (type state)
 pub struct I2c;
 
+/// SDIO pin mode (type state)
+pub struct Sdio;
+
+/// Flash pin mode (type state)
+pub struct Flash;
+
+/// PWM pin mode (type state)
+pub struct Pwm;
+
+/// External PA (FEM) control pin mode (type state)
+pub struct ExtPa;
+
+/// JTAG pin mode (type state)
+pub struct Jtag;
+
+/// IR receiver pin mode (type state)
+pub struct IrRx;
+
+/// Analog pin mode, digital input and pulls disabled (type state)
+pub struct Analog;
+
+/// ADC (GPIP) channel connected to a pin in analog mode
+pub trait AdcChannel {
+    /// Channel number
+    const CHANNEL: u8;
+}
+
+/// DAC (GPDAC) channel connected to a pin in analog mode
+pub trait DacChannel {
+    /// Channel number, 0 for channel A and 1 for channel B
+    const CHANNEL: u8;
+}
+
+/// PWM channel driving a pin in PWM mode
+pub trait PwmPin {
+    /// Channel number
+    const CHANNEL: u8;
+}
+
 #[doc(hidden)]
 pub trait UartPin<SIG> {}
 
@@ -243,8 +573,375 @@ pub trait UartPin<SIG> {}
 
 pub use self::pin::*;
 
+// Implements the embedded-hal digital traits on top of the internal pin traits
+macro_rules! impl_io_pin_traits {
+    ($Pin: ty) => {
+        impl embedded_hal::digital::ErrorType for $Pin {
+            type Error = Infallible;
+        }
+
+        impl InputPin for $Pin {
+            fn is_high(&mut self) -> Result<bool, Self::Error> {
+                Ok(self.is_high_inner())
+            }
+
+            fn is_low(&mut self) -> Result<bool, Self::Error> {
+                Ok(self.is_low_inner())
+            }
+        }
+
+        impl InputPinZero for $Pin {
+            type Error = Infallible;
+
+            fn is_high(&self) -> Result<bool, Self::Error> {
+                Ok(self.is_high_inner())
+            }
+
+            fn is_low(&self) -> Result<bool, Self::Error> {
+                Ok(self.is_low_inner())
+            }
+        }
+
+        impl OutputPin for $Pin {
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                self.set_high_inner();
+                Ok(())
+            }
+
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                self.set_low_inner();
+                Ok(())
+            }
+        }
+
+        impl OutputPinZero for $Pin {
+            type Error = Infallible;
+
+            fn set_high(&mut self) -> Result<(), Self::Error> {
+                self.set_high_inner();
+                Ok(())
+            }
+
+            fn set_low(&mut self) -> Result<(), Self::Error> {
+                self.set_low_inner();
+                Ok(())
+            }
+        }
+
+        impl StatefulOutputPin for $Pin {
+            fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+                Ok(self.is_output_high_inner())
+            }
+
+            fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+                Ok(self.is_output_low_inner())
+            }
+        }
+
+        impl StatefulOutputPinZero for $Pin {
+            fn is_set_high(&self) -> Result<bool, Self::Error> {
+                Ok(self.is_output_high_inner())
+            }
+
+            fn is_set_low(&self) -> Result<bool, Self::Error> {
+                Ok(self.is_output_low_inner())
+            }
+        }
+
+        impl ToggleableOutputPinZero for $Pin {
+            type Error = Infallible;
+
+            fn toggle(&mut self) -> Result<(), Self::Error> {
+                if self.is_output_high_inner() {
+                    self.set_low_inner()
+                } else {
+                    self.set_high_inner()
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+/// Pin function table of the BL602 datasheet (chapter GPIO, "GPIO function")
+///
+/// The `impl_glb!` invocation repeats the functions per pin to generate the conversions, every
+/// entry there is checked against this table at compile time.
+mod datasheet {
+    pub struct Row {
+        pub uart: &'static str,
+        pub spi: &'static str,
+        pub i2c: &'static str,
+        pub sdio: &'static str,
+        pub flash: &'static str,
+        pub pwm: u8,
+        pub ext_pa: u8,
+        pub jtag: &'static str,
+        pub adc: Option<u8>,
+        pub dac: &'static str,
+        pub ir_rx: bool,
+    }
+
+    const fn row(
+        pin: u8,
+        sdio: &'static str,
+        flash: &'static str,
+        adc: Option<u8>,
+        dac: &'static str,
+    ) -> Row {
+        Row {
+            uart: [
+                "sig0", "sig1", "sig2", "sig3", "sig4", "sig5", "sig6", "sig7",
+            ][pin as usize % 8],
+            spi: ["miso", "mosi", "ss", "sclk"][pin as usize % 4],
+            i2c: ["scl", "sda"][pin as usize % 2],
+            sdio,
+            flash,
+            pwm: pin % 5,
+            ext_pa: pin % 4,
+            jtag: ["tms", "tdi", "tck", "tdo"][pin as usize % 4],
+            adc,
+            dac,
+            ir_rx: pin >= 11 && pin <= 13,
+        }
+    }
+
+    // The flash column is incomplete, only the GPIO0 to GPIO2 entries have been checked
+    pub const PINS: [Row; 23] = [
+        row(0, "clk", "d1", None, ""),
+        row(1, "cmd", "d2", None, ""),
+        row(2, "dat0", "d3", None, ""),
+        row(3, "dat1", "", None, ""),
+        row(4, "dat2", "", Some(1), ""),
+        row(5, "dat3", "", Some(4), ""),
+        row(6, "", "", Some(5), ""),
+        row(7, "", "", Some(6), ""),
+        row(8, "", "", None, ""),
+        row(9, "", "", Some(7), ""),
+        row(10, "", "", None, ""),
+        row(11, "", "", None, ""),
+        row(12, "", "", Some(0), ""),
+        row(13, "", "", Some(3), "a"),
+        row(14, "", "", Some(2), "b"),
+        row(15, "", "", None, ""),
+        row(16, "", "", None, ""),
+        row(17, "", "", None, ""),
+        row(18, "", "", Some(8), ""),
+        row(19, "", "", Some(9), ""),
+        row(20, "", "", Some(10), ""),
+        row(21, "", "", Some(11), ""),
+        row(22, "", "", None, ""),
+    ];
+
+    pub const fn str_eq(a: &str, b: &str) -> bool {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut i = 0;
+        while i < a.len() {
+            if a[i] != b[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Bit of an alternate function in the function lists of `impl_glb!`
+    pub const fn function_bit(name: &str) -> u32 {
+        const NAMES: [&str; 9] = [
+            "sdio", "flash", "pwm", "ext_pa", "jtag", "analog", "adc", "dac", "ir",
+        ];
+        let mut i = 0;
+        while i < NAMES.len() {
+            if str_eq(NAMES[i], name) {
+                return 1 << i;
+            }
+            i += 1;
+        }
+        panic!("unknown pin function")
+    }
+
+    impl Row {
+        /// Alternate functions of the pin, as `function_bit`s
+        pub const fn functions(&self) -> u32 {
+            let mut functions = function_bit("pwm") | function_bit("ext_pa") | function_bit("jtag");
+            if !str_eq(self.sdio, "") {
+                functions |= function_bit("sdio");
+            }
+            if !str_eq(self.flash, "") {
+                functions |= function_bit("flash");
+            }
+            if self.adc.is_some() {
+                functions |= function_bit("adc") | function_bit("analog");
+            }
+            if !str_eq(self.dac, "") {
+                functions |= function_bit("dac") | function_bit("analog");
+            }
+            if self.ir_rx {
+                functions |= function_bit("ir");
+            }
+            functions
+        }
+
+        pub const fn adc_is(&self, channel: u8) -> bool {
+            matches!(self.adc, Some(c) if c == channel)
+        }
+    }
+}
+
+// Alternate functions listed in the pin function table of the `impl_glb!` invocation
+macro_rules! impl_pin_function {
+    ($Pini: ident, $i: literal, sdio: $sig: ident) => {
+        const _: () = assert!(
+            datasheet::str_eq(datasheet::PINS[$i].sdio, stringify!($sig)),
+            "SDIO signal of the pin doesn't match the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            paste::paste! {
+                /// Configures the pin to SDIO alternate mode
+                pub fn [<into_sdio_ $sig>](self) -> $Pini<Sdio> {
+                    // 1 -> GPIO_FUN_SDIO
+                    self.into_pin_with_mode(1, true, false, true)
+                }
+            }
+        }
+    };
+    ($Pini: ident, $i: literal, flash: $sig: ident) => {
+        const _: () = assert!(
+            datasheet::str_eq(datasheet::PINS[$i].flash, stringify!($sig)),
+            "flash signal of the pin doesn't match the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            paste::paste! {
+                /// Configures the pin to flash alternate mode
+                pub fn [<into_flash_ $sig>](self) -> $Pini<Flash> {
+                    // 2 -> GPIO_FUN_FLASH
+                    self.into_pin_with_mode(2, false, false, true)
+                }
+            }
+        }
+    };
+    ($Pini: ident, $i: literal, pwm: $ch: literal) => {
+        const _: () = assert!(
+            datasheet::PINS[$i].pwm == $ch,
+            "PWM channel of the pin doesn't match the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            /// Configures the pin to PWM alternate mode, see [`PwmPin`] for the channel
+            pub fn into_pwm(self) -> $Pini<Pwm> {
+                // 8 -> GPIO_FUN_PWM
+                self.into_pin_with_mode(8, false, false, false)
+            }
+        }
+
+        impl PwmPin for $Pini<Pwm> {
+            const CHANNEL: u8 = $ch;
+        }
+    };
+    ($Pini: ident, $i: literal, ext_pa: $n: literal) => {
+        const _: () = assert!(
+            datasheet::PINS[$i].ext_pa == $n,
+            "FEM signal of the pin doesn't match the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            paste::paste! {
+                /// Configures the pin to external PA (FEM) control alternate mode
+                pub fn [<into_ext_pa_ $n>](self) -> $Pini<ExtPa> {
+                    // 9 -> GPIO_FUN_EXT_PA
+                    self.into_pin_with_mode(9, false, false, false)
+                }
+            }
+        }
+    };
+    ($Pini: ident, $i: literal, jtag: $sig: ident) => {
+        const _: () = assert!(
+            datasheet::str_eq(datasheet::PINS[$i].jtag, stringify!($sig)),
+            "JTAG signal of the pin doesn't match the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            paste::paste! {
+                /// Configures the pin to JTAG alternate mode
+                pub fn [<into_jtag_ $sig>](self) -> $Pini<Jtag> {
+                    // 14 -> GPIO_FUN_JTAG
+                    self.into_pin_with_mode(14, true, false, true)
+                }
+            }
+        }
+    };
+    ($Pini: ident, $i: literal, analog) => {
+        impl<MODE> $Pini<MODE> {
+            /// Configures the pin to analog mode for the ADC or DAC
+            ///
+            /// The digital input, the output driver, the pulls and the schmitt trigger are disabled.
+            pub fn into_analog(self) -> $Pini<Analog> {
+                // 10 -> GPIO_FUN_ANALOG, the output driver is disabled before the function switch
+                self.into_pin_with_mode_oe(10, false, false, false, false)
+            }
+        }
+    };
+    ($Pini: ident, $i: literal, adc: $ch: literal) => {
+        const _: () = assert!(
+            datasheet::PINS[$i].adc_is($ch),
+            "ADC channel of the pin doesn't match the datasheet"
+        );
+
+        impl AdcChannel for $Pini<Analog> {
+            const CHANNEL: u8 = $ch;
+        }
+    };
+    ($Pini: ident, $i: literal, dac: a) => {
+        const _: () = assert!(
+            datasheet::str_eq(datasheet::PINS[$i].dac, "a"),
+            "DAC channel of the pin doesn't match the datasheet"
+        );
+
+        impl DacChannel for $Pini<Analog> {
+            const CHANNEL: u8 = 0;
+        }
+    };
+    ($Pini: ident, $i: literal, dac: b) => {
+        const _: () = assert!(
+            datasheet::str_eq(datasheet::PINS[$i].dac, "b"),
+            "DAC channel of the pin doesn't match the datasheet"
+        );
+
+        impl DacChannel for $Pini<Analog> {
+            const CHANNEL: u8 = 1;
+        }
+    };
+    ($Pini: ident, $i: literal, ir: rx) => {
+        const _: () = assert!(
+            datasheet::PINS[$i].ir_rx,
+            "IR receiver input isn't available on the pin according to the datasheet"
+        );
+
+        impl<MODE> $Pini<MODE> {
+            /// Configures the pin as the input of the IR receiver
+            ///
+            /// Only one pin can feed the IR receiver, the last configured one is used.
+            pub fn into_ir_rx(self) -> $Pini<IrRx> {
+                let glb = unsafe { &*pac::GLB::ptr() };
+
+                // see GLB_IR_RX_GPIO_Sel, GPIO11 => 1 up to GPIO13 => 3
+                glb.led_driver
+                    .modify(|_, w| unsafe { w.ir_rx_gpio_sel().bits($i - 10) });
+
+                // 11 -> GPIO_FUN_SWGPIO, the receiver samples the pad input
+                self.into_pin_with_mode(11, false, false, true)
+            }
+        }
+    };
+}
+
 macro_rules! impl_glb {
-    ($($Pini: ident: ($pini: ident, $gpio_cfgctli: ident, $UartSigi: ident, $sigi: ident, $spi_kind: ident, $i2c_kind: ident, $gpio_i: ident, $gpio_int_mode_seti: ident) ,)+) => {
+    ($($Pini: ident: ($i: literal, $pini: ident, $gpio_cfgctli: ident, $UartSigi: ident, $sigi: ident, $spi_kind: ident, $i2c_kind: ident, $gpio_i: ident, $gpio_int_mode_seti: ident, [$($func: ident $(: $arg: tt)?),*]) ,)+) => {
         impl GlbExt for pac::GLB {
             fn split(self) -> Parts {
                 Parts {
@@ -258,6 +955,7 @@ macro_rules! impl_glb {
                     uart_mux6: UartMux6 { _mode: PhantomData },
                     uart_mux7: UartMux7 { _mode: PhantomData },
                     clk_cfg: ClkCfg { _ownership: () },
+                    port: Port { _ownership: () },
                 }
             }
         }
@@ -274,12 +972,13 @@ macro_rules! impl_glb {
             pub uart_mux6: UartMux6<Uart0Cts>,
             pub uart_mux7: UartMux7<Uart0Cts>,
             pub clk_cfg: ClkCfg,
+            pub port: Port,
         }
 
         /// GPIO pins
         pub mod pin {
             use core::marker::PhantomData;
-            use core::convert::Infallible;
+            use core::convert::{Infallible, TryFrom};
             use embedded_hal::digital::{InputPin, OutputPin, StatefulOutputPin};
             use embedded_hal_zero::digital::v2::{
                 InputPin as InputPinZero,
@@ -287,7 +986,10 @@ macro_rules! impl_glb {
                 StatefulOutputPin as StatefulOutputPinZero,
                 ToggleableOutputPin as ToggleableOutputPinZero
             };
+            use crate::clock::Clocks;
             use crate::pac;
+            use crate::pwm;
+            use embedded_time::rate::Hertz;
             use super::*;
 
             /// Simple implementation of InputPin trait to use within EH0 and EH1 impls without name conflicts
@@ -321,6 +1023,14 @@ macro_rules! impl_glb {
                     self.into_pin_with_mode(11, false, false, false)
                 }
 
+                /// Configures the pin to operate as a push-pull output pin without pulls, driven with
+                /// the given strength.
+                pub fn into_push_pull_output_with_drive(self, strength: DriveStrength) -> $Pini<Output<Floating>> {
+                    let mut pin = self.into_floating_output();
+                    pin.set_drive_strength(strength);
+                    pin
+                }
+
                 /// Configures the pin to operate as a pull-up output pin.
                 pub fn into_pull_up_output(self) -> $Pini<Output<PullUp>> {
                     self.into_pin_with_mode(11, true, false, false)
@@ -346,28 +1056,52 @@ macro_rules! impl_glb {
                     self.into_pin_with_mode(11, false, true, true)
                 }
 
+                #[inline]
+                fn into_pin_with_mode<T>(self, mode: u8, pu: bool, pd: bool, ie: bool) -> $Pini<T> {
+                    // If we're an input clear the Output Enable bit as well, else set it.
+                    self.into_pin_with_mode_oe(mode, pu, pd, ie, !ie)
+                }
+
                 paste::paste! {
                     #[inline]
-                    fn into_pin_with_mode<T>(self, mode: u8, pu: bool, pd: bool, ie: bool) -> $Pini<T> {
+                    fn into_pin_with_mode_oe<T>(self, mode: u8, pu: bool, pd: bool, ie: bool, oe: bool) -> $Pini<T> {
                         let glb = unsafe { &*pac::GLB::ptr() };
 
+                        // Release the pad before switching the function, so the pin never drives
+                        // with the old level while becoming an input
+                        if !oe {
+                            glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().clear_bit());
+                        }
+
                         glb.$gpio_cfgctli.modify(|_r, w| unsafe { w
                             .[<reg_ $gpio_i _func_sel>]().bits(mode)
                             .[<reg_ $gpio_i _ie>]().bit(ie) // output
                             .[<reg_ $gpio_i _pu>]().bit(pu)
                             .[<reg_ $gpio_i _pd>]().bit(pd)
-                            .[<reg_ $gpio_i _drv>]().bits(0) // disabled
+                            .[<reg_ $gpio_i _drv>]().bits(0) // DriveStrength::Level0
                             .[<reg_ $gpio_i _smt>]().clear_bit()
                         });
 
-                        // If we're an input clear the Output Enable bit as well, else set it.
-                        glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().bit(!ie));
+                        if oe {
+                            glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().set_bit());
+                        }
 
                         $Pini { _mode: PhantomData }
                     }
                 }
             }
 
+            impl<MODE: Driven> $Pini<MODE> {
+                paste::paste! {
+                    /// Sets the strength of the output driver
+                    pub fn set_drive_strength(&mut self, strength: DriveStrength) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+
+                        glb.$gpio_cfgctli.modify(|_, w| unsafe { w.[<reg_ $gpio_i _drv>]().bits(strength as u8) });
+                    }
+                }
+            }
+
             impl<MODE> $Pini<Input<MODE>> {
                 paste::paste! {
                     /// Enable smitter GPIO input filter
@@ -405,6 +1139,61 @@ macro_rules! impl_glb {
                         // 6 -> GPIO_FUN_I2C_x
                         self.into_pin_with_mode(6, true, false, true)
                     }
+
+                    /// Outputs `source` divided down to `freq` on the pin
+                    ///
+                    /// The clock is generated by the PWM channel of the pin (pin number % 5),
+                    /// which is given back by `release_clock_out`.
+                    ///
+                    /// Returns an error if the source isn't available, see [`ClockOutSource`], or
+                    /// `freq` can't be divided from it.
+                    pub fn into_clock_out(
+                        self,
+                        mut channel: pwm::Channel<{ $i % 5 }>,
+                        source: ClockOutSource,
+                        freq: impl Into<Hertz>,
+                        clocks: &Clocks,
+                    ) -> Result<$Pini<ClockOut>, ClockOutError> {
+                        channel.start(&super::clock_out::waveform(source, freq.into().0, clocks)?);
+                        // 8 -> GPIO_FUN_PWM
+                        Ok(self.into_pin_with_mode(8, false, false, false))
+                    }
+                }
+            }
+
+            impl $Pini<ClockOut> {
+                /// Stops the clock output and gives back the pin as floating input and the PWM channel
+                pub fn release_clock_out(self) -> ($Pini<Input<Floating>>, pwm::Channel<{ $i % 5 }>) {
+                    let mut channel = pwm::Channel { _ownership: () };
+                    channel.stop();
+
+                    (self.into_floating_input(), channel)
+                }
+            }
+
+            impl<MODE> $Pini<MODE> {
+                /// Erases the pin number from the type, see [`ErasedPin`]
+                pub fn degrade(self) -> ErasedPin<MODE> {
+                    ErasedPin { pin: $i, _mode: PhantomData }
+                }
+            }
+
+            impl<MODE> PinId for $Pini<MODE> {
+                fn pin_number(&self) -> u8 {
+                    $i
+                }
+            }
+
+            impl<MODE> TryFrom<ErasedPin<MODE>> for $Pini<MODE> {
+                type Error = ErasedPin<MODE>;
+
+                /// Gives the erased pin back if it is not this pin
+                fn try_from(pin: ErasedPin<MODE>) -> Result<Self, Self::Error> {
+                    if pin.pin == $i {
+                        Ok($Pini { _mode: PhantomData })
+                    } else {
+                        Err(pin)
+                    }
                 }
             }
 
@@ -524,11 +1313,7 @@ macro_rules! impl_glb {
                     }
 
                     fn clear_interrupt_pending_bit(&mut self) {
-                        let glb = unsafe { &*pac::GLB::ptr() };
-
-                        glb.gpio_int_clr1.modify(|_, w| { w
-                                                          .[<reg_ $gpio_i _interrupt_clear>]().clear_bit()
-                        });
+                        clear_interrupts(1 << $i);
                     }
 
                     fn check_interrupt(&self) -> bool {
@@ -536,6 +1321,63 @@ macro_rules! impl_glb {
 
                         glb.gpio_int_stat1.read().[<reg_ $gpio_i _interrupt_status>]().is_set()
                     }
+
+                    fn on_interrupt(&mut self, handler: fn()) {
+                        set_interrupt_handler($i, Some(handler));
+                    }
+
+                    fn remove_interrupt_handler(&mut self) {
+                        set_interrupt_handler($i, None);
+                    }
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<MODE> $Pini<Input<MODE>> {
+                fn wait_for_event(&mut self, event: Event) -> super::wait::InterruptFuture {
+                    self.disable_interrupt();
+                    self.trigger_on_event(event);
+                    self.clear_interrupt_pending_bit();
+
+                    let future = super::wait::InterruptFuture::new($i);
+                    self.enable_interrupt();
+
+                    future
+                }
+            }
+
+            #[cfg(feature = "async")]
+            impl<MODE> embedded_hal_async::digital::Wait for $Pini<Input<MODE>> {
+                async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+                    self.wait_for_event(Event::HighLevel).await;
+                    Ok(())
+                }
+
+                async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+                    self.wait_for_event(Event::NegativeLevel).await;
+                    Ok(())
+                }
+
+                async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+                    self.wait_for_event(Event::PositivePulse).await;
+                    Ok(())
+                }
+
+                async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+                    self.wait_for_event(Event::NegativePulse).await;
+                    Ok(())
+                }
+
+                // There is no trigger on both edges, wait for the opposite level instead so an
+                // edge between reading the level and arming the trigger isn't missed
+                async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+                    let event = if self.is_high_inner() {
+                        Event::NegativeLevel
+                    } else {
+                        Event::HighLevel
+                    };
+                    self.wait_for_event(event).await;
+                    Ok(())
                 }
             }
 
@@ -603,35 +1445,674 @@ macro_rules! impl_glb {
                 }
             }
 
-            )+
-        }
-    };
-}
+            impl<MODE> $Pini<MODE> {
+                paste::paste! {
+                    /// Configures the pin to operate as an open-drain output pin.
+                    ///
+                    /// The output level is fixed low and setting the pin high disables the output
+                    /// driver, so an external or `set_pull_up` pull-up brings the line high.
+                    /// The input stays enabled to read the line level back.
+                    pub fn into_open_drain_output(self) -> $Pini<OpenDrain> {
+                        let glb = unsafe { &*pac::GLB::ptr() };
 
-// There are Pin0 to Pin22, totally 23 pins
+                        glb.gpio_cfgctl32.modify(|_, w| w.[<reg_ $gpio_i _o>]().clear_bit());
+
+                        // Input enabled, so the output driver starts released (high)
+                        self.into_pin_with_mode(11, false, false, true)
+                    }
+
+                    /// Configures the pin to switch between input and output at runtime, starting as an input.
+                    pub fn into_flex(self) -> $Pini<Flex> {
+                        self.into_pin_with_mode(11, false, false, true)
+                    }
+                }
+            }
+
+            impl $Pini<OpenDrain> {
+                paste::paste! {
+                    /// Enables or disables the internal pull-up of the line
+                    pub fn set_pull_up(&mut self, enable: bool) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+
+                        glb.$gpio_cfgctli.modify(|_, w| w.[<reg_ $gpio_i _pu>]().bit(enable));
+                    }
+                }
+            }
+
+            impl $Pini<Flex> {
+                paste::paste! {
+                    /// Enables the output driver, the pin drives the last set output level
+                    pub fn set_as_output(&mut self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+
+                        glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().set_bit());
+                    }
+
+                    /// Disables the output driver, the pin only reads the line level
+                    pub fn set_as_input(&mut self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+
+                        glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().clear_bit());
+                    }
+
+                    /// Checks whether the output driver is enabled
+                    pub fn is_output(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+
+                        glb.gpio_cfgctl34.read().[<reg_ $gpio_i _oe>]().bit_is_set()
+                    }
+                }
+            }
+
+            impl InternalInputPinImpl for $Pini<OpenDrain> {
+                paste::paste! {
+                    fn is_high_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl30.read().[<reg_ $gpio_i _i>]().bit_is_set()
+                    }
+
+                    fn is_low_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl30.read().[<reg_ $gpio_i _i>]().bit_is_clear()
+                    }
+                }
+            }
+
+            // The output level stays low, high releases the line by disabling the driver
+            impl InternalOutputPinImp for $Pini<OpenDrain> {
+                paste::paste! {
+                    fn set_high_inner(&self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().clear_bit())
+                    }
+
+                    fn set_low_inner(&self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl34.modify(|_, w| w.[<reg_ $gpio_i _oe>]().set_bit())
+                    }
+                }
+            }
+
+            impl InternalStatefulOutputImp for $Pini<OpenDrain> {
+                paste::paste! {
+                    fn is_output_high_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl34.read().[<reg_ $gpio_i _oe>]().bit_is_clear()
+                    }
+
+                    fn is_output_low_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl34.read().[<reg_ $gpio_i _oe>]().bit_is_set()
+                    }
+                }
+            }
+
+            impl InternalInputPinImpl for $Pini<Flex> {
+                paste::paste! {
+                    fn is_high_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl30.read().[<reg_ $gpio_i _i>]().bit_is_set()
+                    }
+
+                    fn is_low_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl30.read().[<reg_ $gpio_i _i>]().bit_is_clear()
+                    }
+                }
+            }
+
+            impl InternalOutputPinImp for $Pini<Flex> {
+                paste::paste! {
+                    fn set_high_inner(&self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl32.modify(|_, w| w.[<reg_ $gpio_i _o>]().set_bit())
+                    }
+
+                    fn set_low_inner(&self) {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl32.modify(|_, w| w.[<reg_ $gpio_i _o>]().clear_bit())
+                    }
+                }
+            }
+
+            impl InternalStatefulOutputImp for $Pini<Flex> {
+                paste::paste! {
+                    fn is_output_high_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl32.read().[<reg_ $gpio_i _o>]().bit_is_set()
+                    }
+
+                    fn is_output_low_inner(&self) -> bool {
+                        let glb = unsafe { &*pac::GLB::ptr() };
+                        glb.gpio_cfgctl32.read().[<reg_ $gpio_i _o>]().bit_is_clear()
+                    }
+                }
+            }
+
+            impl_io_pin_traits!($Pini<OpenDrain>);
+            impl_io_pin_traits!($Pini<Flex>);
+
+            const _: () = {
+                let row = &datasheet::PINS[$i];
+                assert!(
+                    datasheet::str_eq(row.uart, stringify!($sigi))
+                        && datasheet::str_eq(row.spi, stringify!($spi_kind))
+                        && datasheet::str_eq(row.i2c, stringify!($i2c_kind)),
+                    "UART, SPI or I2C signal of the pin doesn't match the datasheet"
+                );
+                assert!(
+                    row.functions() == 0 $( | datasheet::function_bit(stringify!($func)) )*,
+                    "alternate functions of the pin don't match the datasheet"
+                );
+            };
+
+            $( impl_pin_function!($Pini, $i, $func $(: $arg)?); )*
+
+            )+
+        }
+    };
+}
+
+// There are Pin0 to Pin22, totally 23 pins
+// The last column lists the other alternate functions of the pin, see bl602_gpio.h
 // todo: generate macros
 impl_glb! {
-    Pin0: (pin0, gpio_cfgctl0, UartSig0, sig0, miso, scl, gpio_0, gpio_int_mode_set1),
-    Pin1: (pin1, gpio_cfgctl0, UartSig1, sig1, mosi, sda, gpio_1, gpio_int_mode_set1),
-    Pin2: (pin2, gpio_cfgctl1, UartSig2, sig2, ss, scl, gpio_2, gpio_int_mode_set1),
-    Pin3: (pin3, gpio_cfgctl1, UartSig3, sig3, sclk, sda, gpio_3, gpio_int_mode_set1),
-    Pin4: (pin4, gpio_cfgctl2, UartSig4, sig4, miso, scl, gpio_4, gpio_int_mode_set1),
-    Pin5: (pin5, gpio_cfgctl2, UartSig5, sig5, mosi, sda, gpio_5, gpio_int_mode_set1),
-    Pin6: (pin6, gpio_cfgctl3, UartSig6, sig6, ss, scl, gpio_6, gpio_int_mode_set1),
-    Pin7: (pin7, gpio_cfgctl3, UartSig7, sig7, sclk, sda, gpio_7, gpio_int_mode_set1),
-    Pin8: (pin8, gpio_cfgctl4, UartSig0, sig0, miso, scl, gpio_8, gpio_int_mode_set1),
-    Pin9: (pin9, gpio_cfgctl4, UartSig1, sig1, mosi, sda, gpio_9, gpio_int_mode_set1),
-    Pin10: (pin10, gpio_cfgctl5, UartSig2, sig2, ss, scl, gpio_10, gpio_int_mode_set2),
-    Pin11: (pin11, gpio_cfgctl5, UartSig3, sig3, sclk, sda, gpio_11, gpio_int_mode_set2),
-    Pin12: (pin12, gpio_cfgctl6, UartSig4, sig4, miso, scl, gpio_12, gpio_int_mode_set2),
-    Pin13: (pin13, gpio_cfgctl6, UartSig5, sig5, mosi, sda, gpio_13, gpio_int_mode_set2),
-    Pin14: (pin14, gpio_cfgctl7, UartSig6, sig6, ss, scl, gpio_14, gpio_int_mode_set2),
-    Pin15: (pin15, gpio_cfgctl7, UartSig7, sig7, sclk, sda, gpio_15, gpio_int_mode_set2),
-    Pin16: (pin16, gpio_cfgctl8, UartSig0, sig0, miso, scl, gpio_16, gpio_int_mode_set2),
-    Pin17: (pin17, gpio_cfgctl8, UartSig1, sig1, mosi, sda, gpio_17, gpio_int_mode_set2),
-    Pin18: (pin18, gpio_cfgctl9, UartSig2, sig2, ss, scl, gpio_18, gpio_int_mode_set2),
-    Pin19: (pin19, gpio_cfgctl9, UartSig3, sig3, sclk, sda, gpio_19, gpio_int_mode_set2),
-    Pin20: (pin20, gpio_cfgctl10, UartSig4, sig4, miso, scl, gpio_20, gpio_int_mode_set3),
-    Pin21: (pin21, gpio_cfgctl10, UartSig5, sig5, mosi, sda, gpio_21, gpio_int_mode_set3),
-    Pin22: (pin22, gpio_cfgctl11, UartSig6, sig6, ss, scl, gpio_22, gpio_int_mode_set3),
+    Pin0: (0, pin0, gpio_cfgctl0, UartSig0, sig0, miso, scl, gpio_0, gpio_int_mode_set1, [sdio: clk, flash: d1, pwm: 0, ext_pa: 0, jtag: tms]),
+    Pin1: (1, pin1, gpio_cfgctl0, UartSig1, sig1, mosi, sda, gpio_1, gpio_int_mode_set1, [sdio: cmd, flash: d2, pwm: 1, ext_pa: 1, jtag: tdi]),
+    Pin2: (2, pin2, gpio_cfgctl1, UartSig2, sig2, ss, scl, gpio_2, gpio_int_mode_set1, [sdio: dat0, flash: d3, pwm: 2, ext_pa: 2, jtag: tck]),
+    Pin3: (3, pin3, gpio_cfgctl1, UartSig3, sig3, sclk, sda, gpio_3, gpio_int_mode_set1, [sdio: dat1, pwm: 3, ext_pa: 3, jtag: tdo]),
+    Pin4: (4, pin4, gpio_cfgctl2, UartSig4, sig4, miso, scl, gpio_4, gpio_int_mode_set1, [sdio: dat2, pwm: 4, ext_pa: 0, jtag: tms, analog, adc: 1]),
+    Pin5: (5, pin5, gpio_cfgctl2, UartSig5, sig5, mosi, sda, gpio_5, gpio_int_mode_set1, [sdio: dat3, pwm: 0, ext_pa: 1, jtag: tdi, analog, adc: 4]),
+    Pin6: (6, pin6, gpio_cfgctl3, UartSig6, sig6, ss, scl, gpio_6, gpio_int_mode_set1, [pwm: 1, ext_pa: 2, jtag: tck, analog, adc: 5]),
+    Pin7: (7, pin7, gpio_cfgctl3, UartSig7, sig7, sclk, sda, gpio_7, gpio_int_mode_set1, [pwm: 2, ext_pa: 3, jtag: tdo, analog, adc: 6]),
+    Pin8: (8, pin8, gpio_cfgctl4, UartSig0, sig0, miso, scl, gpio_8, gpio_int_mode_set1, [pwm: 3, ext_pa: 0, jtag: tms]),
+    Pin9: (9, pin9, gpio_cfgctl4, UartSig1, sig1, mosi, sda, gpio_9, gpio_int_mode_set1, [pwm: 4, ext_pa: 1, jtag: tdi, analog, adc: 7]),
+    Pin10: (10, pin10, gpio_cfgctl5, UartSig2, sig2, ss, scl, gpio_10, gpio_int_mode_set2, [pwm: 0, ext_pa: 2, jtag: tck]),
+    Pin11: (11, pin11, gpio_cfgctl5, UartSig3, sig3, sclk, sda, gpio_11, gpio_int_mode_set2, [pwm: 1, ext_pa: 3, jtag: tdo, ir: rx]),
+    Pin12: (12, pin12, gpio_cfgctl6, UartSig4, sig4, miso, scl, gpio_12, gpio_int_mode_set2, [pwm: 2, ext_pa: 0, jtag: tms, ir: rx, analog, adc: 0]),
+    Pin13: (13, pin13, gpio_cfgctl6, UartSig5, sig5, mosi, sda, gpio_13, gpio_int_mode_set2, [pwm: 3, ext_pa: 1, jtag: tdi, ir: rx, analog, adc: 3, dac: a]),
+    Pin14: (14, pin14, gpio_cfgctl7, UartSig6, sig6, ss, scl, gpio_14, gpio_int_mode_set2, [pwm: 4, ext_pa: 2, jtag: tck, analog, adc: 2, dac: b]),
+    Pin15: (15, pin15, gpio_cfgctl7, UartSig7, sig7, sclk, sda, gpio_15, gpio_int_mode_set2, [pwm: 0, ext_pa: 3, jtag: tdo]),
+    Pin16: (16, pin16, gpio_cfgctl8, UartSig0, sig0, miso, scl, gpio_16, gpio_int_mode_set2, [pwm: 1, ext_pa: 0, jtag: tms]),
+    Pin17: (17, pin17, gpio_cfgctl8, UartSig1, sig1, mosi, sda, gpio_17, gpio_int_mode_set2, [pwm: 2, ext_pa: 1, jtag: tdi]),
+    Pin18: (18, pin18, gpio_cfgctl9, UartSig2, sig2, ss, scl, gpio_18, gpio_int_mode_set2, [pwm: 3, ext_pa: 2, jtag: tck, analog, adc: 8]),
+    Pin19: (19, pin19, gpio_cfgctl9, UartSig3, sig3, sclk, sda, gpio_19, gpio_int_mode_set2, [pwm: 4, ext_pa: 3, jtag: tdo, analog, adc: 9]),
+    Pin20: (20, pin20, gpio_cfgctl10, UartSig4, sig4, miso, scl, gpio_20, gpio_int_mode_set3, [pwm: 0, ext_pa: 0, jtag: tms, analog, adc: 10]),
+    Pin21: (21, pin21, gpio_cfgctl10, UartSig5, sig5, mosi, sda, gpio_21, gpio_int_mode_set3, [pwm: 1, ext_pa: 1, jtag: tdi, analog, adc: 11]),
+    Pin22: (22, pin22, gpio_cfgctl11, UartSig6, sig6, ss, scl, gpio_22, gpio_int_mode_set3, [pwm: 2, ext_pa: 2, jtag: tck]),
+}
+
+/// Pin with its number only known at runtime
+///
+/// Created with `PinN::degrade`, this allows keeping pins in arrays or passing them around
+/// without generics. Use `PinN::try_from` to get the typed pin back.
+pub struct ErasedPin<MODE> {
+    pin: u8,
+    _mode: PhantomData<MODE>,
+}
+
+impl<MODE> ErasedPin<MODE> {
+    /// Returns the number of the pin
+    pub fn pin_number(&self) -> u8 {
+        self.pin
+    }
+
+    #[inline]
+    fn mask(&self) -> u32 {
+        1 << self.pin
+    }
+}
+
+impl<MODE> ErasedPin<Input<MODE>> {
+    fn is_high_inner(&self) -> bool {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        glb.gpio_cfgctl30.read().bits() & self.mask() != 0
+    }
+}
+
+impl<MODE> ErasedPin<Output<MODE>> {
+    fn set_high_inner(&self) {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        glb.gpio_cfgctl32
+            .modify(|r, w| unsafe { w.bits(r.bits() | self.mask()) });
+    }
+
+    fn set_low_inner(&self) {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        glb.gpio_cfgctl32
+            .modify(|r, w| unsafe { w.bits(r.bits() & !self.mask()) });
+    }
+
+    fn is_output_high_inner(&self) -> bool {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        glb.gpio_cfgctl32.read().bits() & self.mask() != 0
+    }
+}
+
+impl<MODE> embedded_hal::digital::ErrorType for ErasedPin<Input<MODE>> {
+    type Error = Infallible;
+}
+
+impl<MODE> embedded_hal::digital::InputPin for ErasedPin<Input<MODE>> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.is_high_inner())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high_inner())
+    }
+}
+
+impl<MODE> embedded_hal_zero::digital::v2::InputPin for ErasedPin<Input<MODE>> {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_high_inner())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.is_high_inner())
+    }
+}
+
+impl<MODE> embedded_hal::digital::ErrorType for ErasedPin<Output<MODE>> {
+    type Error = Infallible;
+}
+
+impl<MODE> embedded_hal::digital::OutputPin for ErasedPin<Output<MODE>> {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high_inner();
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low_inner();
+        Ok(())
+    }
+}
+
+impl<MODE> embedded_hal_zero::digital::v2::OutputPin for ErasedPin<Output<MODE>> {
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high_inner();
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low_inner();
+        Ok(())
+    }
+}
+
+impl<MODE> embedded_hal::digital::StatefulOutputPin for ErasedPin<Output<MODE>> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.is_output_high_inner())
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.is_output_high_inner())
+    }
+}
+
+impl<MODE> embedded_hal_zero::digital::v2::StatefulOutputPin for ErasedPin<Output<MODE>> {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_output_high_inner())
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.is_output_high_inner())
+    }
+}
+
+impl<MODE> embedded_hal_zero::digital::v2::ToggleableOutputPin for ErasedPin<Output<MODE>> {
+    type Error = Infallible;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        if self.is_output_high_inner() {
+            self.set_low_inner()
+        } else {
+            self.set_high_inner()
+        }
+        Ok(())
+    }
+}
+
+/// All GPIO levels at once
+///
+/// Unlike the pins, writes go to every output pin in the mask, whoever owns it, except the
+/// [`Locked`] ones. Use a [`PinGroup`] to only write pins you own.
+pub struct Port {
+    pub(crate) _ownership: (),
+}
+
+impl Port {
+    /// Reads the input levels, bit N is the level of pin N
+    pub fn read(&self) -> u32 {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        glb.gpio_cfgctl30.read().bits() & ((1 << PIN_COUNT) - 1)
+    }
+
+    /// Reads the output levels, bit N is the level pin N drives
+    pub fn read_output(&self) -> u32 {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        glb.gpio_cfgctl32.read().bits() & ((1 << PIN_COUNT) - 1)
+    }
+
+    /// Sets the output level of the pins in `mask` to the bits of `value`, in one register write
+    ///
+    /// Locked pins in `mask` are left alone.
+    pub fn write_masked(&mut self, mask: u32, value: u32) {
+        write_outputs(mask & !locked_pins(), value);
+    }
+
+    /// Puts the pins in `mask` in their lowest leakage state before sleeping
+    ///
+    /// The pins become software GPIOs with input, output, pulls and schmitt trigger disabled.
+    /// Take a [`Snapshot`] before to bring them back on wake-up. Locked pins in `mask` are
+    /// left alone.
+    pub fn park(&mut self, mask: u32) {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        let mask = mask & !locked_pins();
+
+        glb.gpio_cfgctl34
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+
+        for pin in (0..PIN_COUNT).filter(|pin| mask & (1 << pin) != 0) {
+            let cfgctl = unsafe { cfgctl_ptr(pin / 2) };
+            let shift = (pin % 2) * 16;
+            unsafe {
+                let bits = cfgctl.read_volatile() & !(0xFFFF << shift);
+                // 11 -> GPIO_FUN_SWGPIO in func_sel, everything else off
+                cfgctl.write_volatile(bits | (11 << 8) << shift);
+            }
+        }
+    }
+}
+
+/// Swaps the JTAG signals of a group of four pins, GPIO `4 * group` to `4 * group + 3`
+///
+/// Sets bit `group` of `jtag_swap_set`, see GLB_JTAG_Sig_Swap_Set. The signal names of the
+/// `into_jtag_*` conversions of the group's pins are those without the swap.
+pub fn swap_jtag_signals(group: u8, swap: bool) {
+    // 23 pins, so six groups
+    assert!(group < 6, "JTAG swap group out of range");
+    assert!(
+        locked_pins() & (0xF << (4 * group)) == 0,
+        "JTAG swap group has locked pins"
+    );
+
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    // Other groups may be swapped from an interrupt
+    critical_section::with(|_| {
+        glb.glb_parm.modify(|r, w| unsafe {
+            let bits = r.jtag_swap_set().bits() & !(1 << group);
+            w.jtag_swap_set().bits(bits | ((swap as u8) << group))
+        })
+    });
+}
+
+// Pin 2N is in the lower and pin 2N+1 in the upper half of a config register
+const CFGCTL_COUNT: usize = PIN_COUNT.div_ceil(2);
+
+/// Pointer to `gpio_cfgctl<index>`, the config registers are consecutive words
+unsafe fn cfgctl_ptr(index: usize) -> *mut u32 {
+    let glb = &*pac::GLB::ptr();
+
+    (&glb.gpio_cfgctl0 as *const _ as *mut u32).add(index)
+}
+
+/// Configuration of all pins, to bring them back after sleeping
+///
+/// Holds the function, input/output enable, pulls, drive strength, schmitt trigger and
+/// output level of every pin.
+#[derive(Clone, Copy)]
+pub struct Snapshot {
+    cfgctl: [u32; CFGCTL_COUNT],
+    output: u32,
+    output_enable: u32,
+}
+
+impl Snapshot {
+    /// Reads the current configuration of all pins
+    pub fn capture(_port: &mut Port) -> Self {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        let mut cfgctl = [0; CFGCTL_COUNT];
+        for (index, bits) in cfgctl.iter_mut().enumerate() {
+            *bits = unsafe { cfgctl_ptr(index).read_volatile() };
+        }
+
+        Snapshot {
+            cfgctl,
+            output: glb.gpio_cfgctl32.read().bits(),
+            output_enable: glb.gpio_cfgctl34.read().bits(),
+        }
+    }
+
+    /// Writes the captured configuration back, except for the locked pins
+    pub fn restore(&self, _port: &mut Port) {
+        let glb = unsafe { &*pac::GLB::ptr() };
+        let locked = locked_pins();
+        let keep = |current: u32, captured: u32| (current & locked) | (captured & !locked);
+
+        // Output levels first and the drivers last, so no pin glitches on its way back
+        glb.gpio_cfgctl32
+            .modify(|r, w| unsafe { w.bits(keep(r.bits(), self.output)) });
+        for (index, bits) in self.cfgctl.iter().enumerate() {
+            // Pin 2N in the lower and pin 2N+1 in the upper half
+            let locked_halves = (0..2)
+                .filter(|half| locked & (1 << (2 * index + half)) != 0)
+                .fold(0, |halves, half| halves | (0xFFFF << (16 * half)));
+            unsafe {
+                let cfgctl = cfgctl_ptr(index);
+                let current = cfgctl.read_volatile();
+                cfgctl.write_volatile((current & locked_halves) | (bits & !locked_halves));
+            }
+        }
+        glb.gpio_cfgctl34
+            .modify(|r, w| unsafe { w.bits(keep(r.bits(), self.output_enable)) });
+    }
+}
+
+// Pins wrapped in a `Locked`, bit N is pin N
+static LOCKED_PINS: AtomicU32 = AtomicU32::new(0);
+
+fn locked_pins() -> u32 {
+    LOCKED_PINS.load(Ordering::Relaxed)
+}
+
+/// Pin whose number is known, typed or erased
+pub trait PinId {
+    /// Returns the number of the pin
+    fn pin_number(&self) -> u8;
+}
+
+impl<MODE> PinId for ErasedPin<MODE> {
+    fn pin_number(&self) -> u8 {
+        self.pin
+    }
+}
+
+/// Pin reserved by the board support, it can be used but not reconfigured
+///
+/// Only the digital traits of the wrapped pin are available, the `into_*` conversions and
+/// other configuration methods are not. [`Port`] and [`Snapshot`] skip locked pins too.
+pub struct Locked<PIN> {
+    pin: PIN,
+}
+
+impl<PIN: PinId> Locked<PIN> {
+    /// Locks the configuration of `pin`
+    pub fn new(pin: PIN) -> Self {
+        LOCKED_PINS.fetch_or(1 << pin.pin_number(), Ordering::Relaxed);
+
+        Locked { pin }
+    }
+}
+
+impl<PIN: embedded_hal::digital::ErrorType> embedded_hal::digital::ErrorType for Locked<PIN> {
+    type Error = PIN::Error;
+}
+
+impl<PIN: embedded_hal::digital::InputPin> embedded_hal::digital::InputPin for Locked<PIN> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_low()
+    }
+}
+
+impl<PIN: embedded_hal::digital::OutputPin> embedded_hal::digital::OutputPin for Locked<PIN> {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_low()
+    }
+}
+
+impl<PIN: embedded_hal::digital::StatefulOutputPin> embedded_hal::digital::StatefulOutputPin
+    for Locked<PIN>
+{
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_set_high()
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_set_low()
+    }
+}
+
+impl<PIN: embedded_hal_zero::digital::v2::InputPin> embedded_hal_zero::digital::v2::InputPin
+    for Locked<PIN>
+{
+    type Error = PIN::Error;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.pin.is_low()
+    }
+}
+
+impl<PIN: embedded_hal_zero::digital::v2::OutputPin> embedded_hal_zero::digital::v2::OutputPin
+    for Locked<PIN>
+{
+    type Error = PIN::Error;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_low()
+    }
+}
+
+impl<PIN: embedded_hal_zero::digital::v2::StatefulOutputPin>
+    embedded_hal_zero::digital::v2::StatefulOutputPin for Locked<PIN>
+{
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        self.pin.is_set_high()
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.pin.is_set_low()
+    }
+}
+
+impl<PIN: embedded_hal_zero::digital::v2::ToggleableOutputPin>
+    embedded_hal_zero::digital::v2::ToggleableOutputPin for Locked<PIN>
+{
+    type Error = PIN::Error;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.pin.toggle()
+    }
+}
+
+fn write_outputs(mask: u32, value: u32) {
+    let glb = unsafe { &*pac::GLB::ptr() };
+
+    glb.gpio_cfgctl32
+        .modify(|r, w| unsafe { w.bits((r.bits() & !mask) | (value & mask)) });
+}
+
+/// Pins read or written together, bit K of a value is the level of the K-th pin
+///
+/// ```rust
+/// let mut bus = PinGroup::new([
+///     parts.pin0.into_floating_output().degrade(),
+///     parts.pin1.into_floating_output().degrade(),
+///     parts.pin3.into_floating_output().degrade(),
+/// ]);
+/// bus.write(0b101);
+/// ```
+pub struct PinGroup<MODE, const N: usize> {
+    pins: [ErasedPin<MODE>; N],
+    mask: u32,
+}
+
+impl<MODE, const N: usize> PinGroup<MODE, N> {
+    /// Groups `pins`, the first pin maps to bit 0
+    pub fn new(pins: [ErasedPin<MODE>; N]) -> Self {
+        let mask = pins.iter().fold(0, |mask, pin| mask | pin.mask());
+
+        PinGroup { pins, mask }
+    }
+
+    /// Returns the pins of the group
+    pub fn release(self) -> [ErasedPin<MODE>; N] {
+        self.pins
+    }
+
+    /// Moves bit K of `value` to the bit of the K-th pin
+    fn scatter(&self, value: u32) -> u32 {
+        self.pins
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| value & (1 << k) != 0)
+            .fold(0, |bits, (_, pin)| bits | pin.mask())
+    }
+
+    /// Moves the bit of the K-th pin to bit K
+    fn gather(&self, bits: u32) -> u32 {
+        self.pins
+            .iter()
+            .enumerate()
+            .filter(|(_, pin)| bits & pin.mask() != 0)
+            .fold(0, |value, (k, _)| value | (1 << k))
+    }
+}
+
+impl<MODE, const N: usize> PinGroup<Input<MODE>, N> {
+    /// Reads the levels of the pins
+    pub fn read(&self) -> u32 {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        self.gather(glb.gpio_cfgctl30.read().bits())
+    }
+}
+
+impl<MODE, const N: usize> PinGroup<Output<MODE>, N> {
+    /// Sets the levels of all pins with one register write
+    pub fn write(&mut self, value: u32) {
+        write_outputs(self.mask, self.scatter(value));
+    }
+
+    /// Reads the levels the pins drive
+    pub fn read_output(&self) -> u32 {
+        let glb = unsafe { &*pac::GLB::ptr() };
+
+        self.gather(glb.gpio_cfgctl32.read().bits())
+    }
 }
@@ -24,7 +24,12 @@ use embedded_hal_zero::blocking::i2c::Write as WriteZero;
 use embedded_time::rate::Hertz;
 
 use crate::delay::McycleDelay;
-use crate::{clock::Clocks, pac};
+use crate::{
+    clock::{
+        reset_peripheral, set_peripheral_clock, ClockDependent, ClockError, Clocks, Peripheral,
+    },
+    pac,
+};
 
 use self::private::Sealed;
 
@@ -105,6 +110,68 @@ pub struct I2c<I2C, PINS> {
     pins: PINS,
     /// timeout (in microseconds)
     timeout: u16,
+    /// bus frequency
+    freq: Hertz<u32>,
+    /// clocks the bus frequency is derived from
+    clocks: Clocks,
+}
+
+/// Computes the register value of the phase lengths for `freq` derived from the I2C clock
+fn phase_len(freq: Hertz<u32>, clocks: &Clocks) -> Result<u8, ClockError> {
+    // length of phase 0,1,2 and 3
+    // needs to be divided by four
+    let len = clocks.i2c_clk().0 / freq.0 / 4;
+    if len > 256 || len <= 1 {
+        // from the RM: Note: This value should not be set to 8’d0, adjust source
+        // clock rate instead if higher I2C clock rate is required
+        return Err(ClockError::UnreachableDriverClk {
+            requested: freq.0,
+            source: clocks.i2c_clk().0,
+        });
+    }
+
+    Ok((len - 1) as u8)
+}
+
+/// Writes the start, stop and data phase lengths for `freq` derived from the I2C clock
+fn set_frequency(i2c: &pac::I2C, freq: Hertz<u32>, clocks: &Clocks) {
+    let len = match phase_len(freq, clocks) {
+        Ok(len) => len,
+        Err(_) => panic!("Cannot reach the desired I2C frequency"),
+    };
+
+    i2c.i2c_prd_start.modify(|_r, w| unsafe {
+        w.cr_i2c_prd_s_ph_0()
+            .bits(len)
+            .cr_i2c_prd_s_ph_1()
+            .bits(len)
+            .cr_i2c_prd_s_ph_2()
+            .bits(len)
+            .cr_i2c_prd_s_ph_3()
+            .bits(len)
+    });
+
+    i2c.i2c_prd_stop.modify(|_r, w| unsafe {
+        w.cr_i2c_prd_p_ph_0()
+            .bits(len)
+            .cr_i2c_prd_p_ph_1()
+            .bits(len)
+            .cr_i2c_prd_p_ph_2()
+            .bits(len)
+            .cr_i2c_prd_p_ph_3()
+            .bits(len)
+    });
+
+    i2c.i2c_prd_data.modify(|_r, w| unsafe {
+        w.cr_i2c_prd_d_ph_0()
+            .bits(len)
+            .cr_i2c_prd_d_ph_1()
+            .bits(len)
+            .cr_i2c_prd_d_ph_2()
+            .bits(len)
+            .cr_i2c_prd_d_ph_3()
+            .bits(len)
+    });
 }
 
 impl<PINS> I2c<pac::I2C, PINS>
@@ -123,61 +190,33 @@ where
     where
         PINS: Pins<pac::I2C>,
     {
-        // length of phase 0,1,2 and 3
-        // needs to be divided by four
-        let len = clocks.i2c_clk().0 / freq.0 / 4;
-        if len > 256 || len <= 1 {
-            // from the RM: Note: This value should not be set to 8’d0, adjust source
-            // clock rate instead if higher I2C clock rate is required
-            panic!("Cannot reach the desired I2C frequency");
-        }
-
-        let len = (len - 1) as u8;
-
-        i2c.i2c_prd_start.modify(|_r, w| unsafe {
-            w.cr_i2c_prd_s_ph_0()
-                .bits(len)
-                .cr_i2c_prd_s_ph_1()
-                .bits(len)
-                .cr_i2c_prd_s_ph_2()
-                .bits(len)
-                .cr_i2c_prd_s_ph_3()
-                .bits(len)
-        });
+        set_peripheral_clock(Peripheral::I2c, true);
 
-        i2c.i2c_prd_stop.modify(|_r, w| unsafe {
-            w.cr_i2c_prd_p_ph_0()
-                .bits(len)
-                .cr_i2c_prd_p_ph_1()
-                .bits(len)
-                .cr_i2c_prd_p_ph_2()
-                .bits(len)
-                .cr_i2c_prd_p_ph_3()
-                .bits(len)
-        });
-
-        i2c.i2c_prd_data.modify(|_r, w| unsafe {
-            w.cr_i2c_prd_d_ph_0()
-                .bits(len)
-                .cr_i2c_prd_d_ph_1()
-                .bits(len)
-                .cr_i2c_prd_d_ph_2()
-                .bits(len)
-                .cr_i2c_prd_d_ph_3()
-                .bits(len)
-        });
+        set_frequency(&i2c, freq, &clocks);
 
         I2c {
             i2c,
             pins,
             timeout: 2048,
+            freq,
+            clocks,
         }
     }
 
     pub fn release(self) -> (pac::I2C, PINS) {
+        set_peripheral_clock(Peripheral::I2c, false);
+
         (self.i2c, self.pins)
     }
 
+    /// Pulses the I2C software reset and re-applies the bus frequency
+    ///
+    /// Use this to recover a wedged I2C; the FIFOs are emptied by the reset.
+    pub fn reset(&mut self) {
+        reset_peripheral(Peripheral::I2c);
+        set_frequency(&self.i2c, self.freq, &self.clocks);
+    }
+
     /// Set the timeout (in microseconds) when waiting for fifo (rx and tx).
     /// This defaults to 2000us (2 milliseconds)
     pub fn set_timeout(&mut self, timeout: u16) {
@@ -192,6 +231,25 @@ where
     }
 }
 
+impl<PINS> ClockDependent for I2c<pac::I2C, PINS>
+where
+    PINS: Pins<pac::I2C>,
+{
+    fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+        phase_len(self.freq, clocks).map(|_| ())
+    }
+
+    fn suspend(&mut self) -> Result<(), ClockError> {
+        // Transfers are blocking, the master is already disabled between them
+        Ok(())
+    }
+
+    fn resume(&mut self, clocks: &Clocks) {
+        self.clocks = *clocks;
+        set_frequency(&self.i2c, self.freq, clocks);
+    }
+}
+
 impl<PINS> i2cAlpha::ErrorType for I2c<pac::I2C, PINS> {
     type Error = Error;
 }
@@ -15,12 +15,17 @@
     }
   ```
 
+  GPIO interrupts can also be handled per pin with `InterruptPin::on_interrupt`, `Gpio` is then
+  only called for pending pins without a registered handler.
+
   ## The following functions can be implemented as interrupt handlers
   ```rust
     fn Gpio();
     fn TimerCh0();
     fn TimerCh1();
     fn Watchdog();
+    fn Uart0();
+    fn Uart1();
   ```
 */
 
@@ -31,6 +36,8 @@ extern "C" {
     fn TimerCh0(trap_frame: &mut TrapFrame);
     fn TimerCh1(trap_frame: &mut TrapFrame);
     fn Watchdog(trap_frame: &mut TrapFrame);
+    fn Uart0(trap_frame: &mut TrapFrame);
+    fn Uart1(trap_frame: &mut TrapFrame);
 }
 
 // see components\bl602\bl602_std\bl602_std\RISCV\Core\Include\clic.h
@@ -44,6 +51,8 @@ const GPIO_IRQ: u32 = IRQ_NUM_BASE + 44;
 const TIMER_CH0_IRQ: u32 = IRQ_NUM_BASE + 36;
 const TIMER_CH1_IRQ: u32 = IRQ_NUM_BASE + 37;
 const WATCHDOG_IRQ: u32 = IRQ_NUM_BASE + 38;
+const UART0_IRQ: u32 = IRQ_NUM_BASE + 29;
+const UART1_IRQ: u32 = IRQ_NUM_BASE + 30;
 
 #[doc(hidden)]
 #[no_mangle]
@@ -137,10 +146,26 @@ pub unsafe extern "C" fn start_trap_rust_hal(trap_frame: *mut TrapFrame) {
 
             match interrupt {
                 Interrupt::Unknown => _start_trap_rust(trap_frame),
-                Interrupt::Gpio => Gpio(trap_frame.as_mut().unwrap()),
+                Interrupt::Gpio => {
+                    if crate::gpio::dispatch_interrupts() {
+                        Gpio(trap_frame.as_mut().unwrap())
+                    } else {
+                        clear_interrupt(Interrupt::Gpio)
+                    }
+                }
                 Interrupt::TimerCh0 => TimerCh0(trap_frame.as_mut().unwrap()),
                 Interrupt::TimerCh1 => TimerCh1(trap_frame.as_mut().unwrap()),
                 Interrupt::Watchdog => Watchdog(trap_frame.as_mut().unwrap()),
+                Interrupt::Uart0 => {
+                    if !crate::serial::on_buffered_interrupt(0) {
+                        Uart0(trap_frame.as_mut().unwrap())
+                    }
+                }
+                Interrupt::Uart1 => {
+                    if !crate::serial::on_buffered_interrupt(1) {
+                        Uart1(trap_frame.as_mut().unwrap())
+                    }
+                }
             };
         }
     }
@@ -159,6 +184,10 @@ pub enum Interrupt {
     /// Watchdog Timer Interrupt
     /// Used when WDT is configured in Interrupt mode using ConfiguredWatchdog0::set_mode()
     Watchdog,
+    /// UART0 Interrupt, see `Serial::listen`
+    Uart0,
+    /// UART1 Interrupt, see `Serial::listen`
+    Uart1,
 }
 
 impl Interrupt {
@@ -169,6 +198,8 @@ impl Interrupt {
             Interrupt::TimerCh0 => TIMER_CH0_IRQ,
             Interrupt::TimerCh1 => TIMER_CH1_IRQ,
             Interrupt::Watchdog => WATCHDOG_IRQ,
+            Interrupt::Uart0 => UART0_IRQ,
+            Interrupt::Uart1 => UART1_IRQ,
         }
     }
 
@@ -178,6 +209,8 @@ impl Interrupt {
             TIMER_CH0_IRQ => Interrupt::TimerCh0,
             TIMER_CH1_IRQ => Interrupt::TimerCh1,
             WATCHDOG_IRQ => Interrupt::Watchdog,
+            UART0_IRQ => Interrupt::Uart0,
+            UART1_IRQ => Interrupt::Uart1,
             _ => Interrupt::Unknown,
         }
     }
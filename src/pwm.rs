@@ -0,0 +1,115 @@
+/*!
+  # PWM
+  The PWM peripheral has five channels. Channel N drives the pins whose number modulo 5 is N,
+  see `gpio::PwmPin`.
+
+  The channels are handed out as ownership tokens, so a channel can only be programmed by
+  the code holding it.
+
+  ## Example
+  ```rust
+    use bl602_hal::pwm::PwmExt;
+
+    let channels = dp.PWM.split();
+    let pin = parts.pin3.into_clock_out(channels.channel3, ClockOutSource::Xtal, 1_000_000.Hz(), &clocks).unwrap();
+  ```
+*/
+
+use crate::clock::{set_peripheral_clock, Peripheral};
+use crate::pac;
+use paste::paste;
+
+/// PWM channel N (ownership token)
+pub struct Channel<const N: u8> {
+    pub(crate) _ownership: (),
+}
+
+/// PWM channels
+pub struct Channels {
+    pub channel0: Channel<0>,
+    pub channel1: Channel<1>,
+    pub channel2: Channel<2>,
+    pub channel3: Channel<3>,
+    pub channel4: Channel<4>,
+}
+
+/// Extension trait to split PWM peripheral into independent channels
+pub trait PwmExt {
+    fn split(self) -> Channels;
+}
+
+impl PwmExt for pac::PWM {
+    fn split(self) -> Channels {
+        set_peripheral_clock(Peripheral::Pwm, true);
+
+        Channels {
+            channel0: Channel { _ownership: () },
+            channel1: Channel { _ownership: () },
+            channel2: Channel { _ownership: () },
+            channel3: Channel { _ownership: () },
+            channel4: Channel { _ownership: () },
+        }
+    }
+}
+
+/// Channel settings, the output frequency is `source / clk_div / period`
+///
+/// The output is high while the counter is between `thre1` and `thre2`.
+pub(crate) struct Waveform {
+    /// `reg_clk_sel` value: 0 => XCLK, 1 => BCLK, 2 => 32K
+    pub clk_sel: u8,
+    pub clk_div: u16,
+    pub period: u16,
+    pub thre1: u16,
+    pub thre2: u16,
+}
+
+// Waiting for a stop needs a running source clock, give up after a while
+const STOP_TIMEOUT: u32 = 0x1000;
+
+macro_rules! impl_channel {
+    ($($ch: literal),+) => {
+        paste! {
+            $(
+                impl Channel<$ch> {
+                    /// Programs `waveform` and starts the channel
+                    pub(crate) fn start(&mut self, waveform: &Waveform) {
+                        let pwm = unsafe { &*pac::PWM::ptr() };
+
+                        // Stop the channel before changing the clock
+                        self.stop();
+
+                        pwm.[<pwm $ch _config>]
+                            .modify(|_, w| unsafe { w.reg_clk_sel().bits(waveform.clk_sel) });
+                        pwm.[<pwm $ch _clkdiv>]
+                            .write(|w| unsafe { w.pwm_clk_div().bits(waveform.clk_div) });
+                        pwm.[<pwm $ch _thre1>]
+                            .write(|w| unsafe { w.pwm_thre1().bits(waveform.thre1) });
+                        pwm.[<pwm $ch _thre2>]
+                            .write(|w| unsafe { w.pwm_thre2().bits(waveform.thre2) });
+                        pwm.[<pwm $ch _period>]
+                            .write(|w| unsafe { w.pwm_period().bits(waveform.period) });
+
+                        pwm.[<pwm $ch _config>].modify(|_, w| w.pwm_stop_en().clear_bit());
+                    }
+
+                    /// Stops the channel and waits until the counter has stopped
+                    pub(crate) fn stop(&mut self) {
+                        let pwm = unsafe { &*pac::PWM::ptr() };
+
+                        pwm.[<pwm $ch _config>].modify(|_, w| w.pwm_stop_en().set_bit());
+
+                        let mut timeout = STOP_TIMEOUT;
+                        while pwm.[<pwm $ch _config>].read().pwm_sts_top().bit_is_clear()
+                            && timeout > 0
+                        {
+                            timeout -= 1;
+                        }
+                    }
+                }
+            )+
+        }
+    };
+}
+
+impl_channel!(0, 1, 2, 3, 4);
@@ -1,26 +1,62 @@
 //! Serial communication
 use self::private::Sealed;
-use crate::clock::Clocks;
+use crate::clock::{
+    reset_peripheral, set_peripheral_clock, ClockDependent, ClockError, Clocks, Peripheral,
+};
+use crate::delay::McycleDelay;
+use crate::interrupts::{clear_interrupt, disable_interrupt, enable_interrupt, Interrupt};
 use crate::pac;
+use core::cell::{Cell, UnsafeCell};
 use core::fmt;
+use core::marker::PhantomData;
 use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use critical_section::Mutex;
 use embedded_hal_nb;
 use embedded_hal_nb::serial::Write;
-use embedded_time::rate::{Baud, Extensions};
+use embedded_time::duration::{Extensions as _, Milliseconds};
+use embedded_time::rate::{Baud, Extensions, Hertz};
 use nb::block;
 
 /// Serial error
-#[derive(Debug)]
+///
+/// The BL602 UART has no framing or noise detection, `Framing` and `Noise` are never returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Error {
     /// Framing error
     Framing,
     /// Noise error
     Noise,
-    /// RX buffer overrun
+    /// RX FIFO overflowed, reported after the bytes received before the overflow have been read
     Overrun,
-    /// Parity check error
+    /// Parity check error on a received byte
+    ///
+    /// The UART only latches that some byte failed the check, not which one, so nothing is
+    /// dropped and the bytes are read as received.
     Parity,
+    /// CTS held the transmitter off for longer than `Config::cts_timeout`
+    FlowControl,
+}
+
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Parity => embedded_io::ErrorKind::InvalidData,
+            Error::FlowControl => embedded_io::ErrorKind::TimedOut,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+/// Serial configuration error, returned by `Serial::new`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The baudrate can't be divided from the UART clock
+    Baudrate(ClockError),
+    /// The RX de-glitch cycle count is above 15
+    RxDeglitch(u8),
 }
 
 impl embedded_hal_nb::serial::Error for Error {
@@ -30,6 +66,7 @@ impl embedded_hal_nb::serial::Error for Error {
             Error::Noise => embedded_hal_nb::serial::ErrorKind::Noise,
             Error::Overrun => embedded_hal_nb::serial::ErrorKind::Overrun,
             Error::Parity => embedded_hal_nb::serial::ErrorKind::Parity,
+            Error::FlowControl => embedded_hal_nb::serial::ErrorKind::Other,
         }
     }
 }
@@ -42,6 +79,10 @@ pub struct Config {
     pub parity: Parity,
     pub stopbits: StopBits,
     pub wordlength: WordLength,
+    /// RX de-glitch filter length in UART clock cycles, 0 disables the filter
+    pub rx_deglitch: u8,
+    /// How long CTS may hold the transmitter off before writes report `Error::FlowControl`
+    pub cts_timeout: Milliseconds<u32>,
 }
 
 impl Config {
@@ -79,6 +120,39 @@ impl Config {
 
         self
     }
+
+    /// Sets the number of data bits per word
+    pub fn wordlength(mut self, wordlength: WordLength) -> Self {
+        self.wordlength = wordlength;
+
+        self
+    }
+
+    /// Sets the order of the bits on the wire
+    pub fn bit_order(mut self, order: Order) -> Self {
+        self.order = order;
+
+        self
+    }
+
+    /// Filters out glitches on RX shorter than `cycles` UART clock cycles
+    ///
+    /// The hardware counter is 4 bits wide, so `cycles` can be at most 15, `Serial::new`
+    /// returns `ConfigError::RxDeglitch` otherwise. 0 disables the filter.
+    pub fn rx_deglitch(mut self, cycles: u8) -> Self {
+        self.rx_deglitch = cycles;
+
+        self
+    }
+
+    /// Sets how long CTS may hold the transmitter off, only used with CTS pins
+    ///
+    /// A write or flush making no progress for longer than this returns `Error::FlowControl`.
+    pub fn cts_timeout(mut self, timeout: impl Into<Milliseconds<u32>>) -> Self {
+        self.cts_timeout = timeout.into();
+
+        self
+    }
 }
 
 impl Default for Config {
@@ -89,6 +163,8 @@ impl Default for Config {
             parity: Parity::ParityNone,
             stopbits: StopBits::STOP1,
             wordlength: WordLength::Eight,
+            rx_deglitch: 0,
+            cts_timeout: 100_u32.milliseconds(),
         }
     }
 }
@@ -129,13 +205,18 @@ pub enum StopBits {
 /// Word length
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum WordLength {
+    /// 5 data bits
     Five,
+    /// 6 data bits
     Six,
+    /// 7 data bits
     Seven,
+    /// 8 data bits
     Eight,
 }
 
 /// Interrupt event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Event {
     /// UART RX FIFO error interrupt
     RxFifoError,
@@ -155,10 +236,117 @@ pub enum Event {
     TxTransferEnd,
 }
 
+impl Event {
+    /// Bit of the event in the uart_int_* registers
+    fn mask(self) -> u32 {
+        1 << match self {
+            Event::TxTransferEnd => 0,
+            Event::RxTransferEnd => 1,
+            Event::TxFifoReady => 2,
+            Event::RxFifoReady => 3,
+            Event::RxTimeout => 4,
+            Event::RxParityError => 5,
+            Event::TxFifoError => 6,
+            Event::RxFifoError => 7,
+        }
+    }
+}
+
 /// Serial abstraction
 pub struct Serial<UART, PINS> {
     uart: UART,
     pins: PINS,
+    config: Config,
+    clocks: Clocks,
+    /// Event masks enabled with `listen`, restored after a reset
+    listening: u32,
+    cts: CtsWatch,
+    /// Bit period in UART clock cycles, derived from `config` and `clocks`
+    bit_period: u16,
+}
+
+/// Tracks how long CTS has been holding the transmitter off
+#[derive(Clone, Copy)]
+struct CtsWatch {
+    /// Core clock cycles the transmitter may stall, `None` without flow control
+    timeout: Option<u64>,
+    /// Cycle count at which the transmitter was last seen making no progress
+    stalled_since: Option<u64>,
+    /// Free TX FIFO slots when the stall started
+    free: u8,
+}
+
+impl CtsWatch {
+    fn new(has_cts: bool, config: &Config, clocks: &Clocks) -> Self {
+        let timeout = config.cts_timeout.0 as u64 * clocks.sysclk().0 as u64 / 1000;
+
+        CtsWatch {
+            timeout: if has_cts { Some(timeout) } else { None },
+            stalled_since: None,
+            free: 0,
+        }
+    }
+
+    /// Turns a stalled transmitter into `Error::FlowControl` once the timeout has passed
+    fn stalled(&mut self, free: u8) -> nb::Error<Error> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return nb::Error::WouldBlock,
+        };
+        let now = McycleDelay::get_cycle_count();
+
+        match self.stalled_since {
+            Some(since) if free == self.free => {
+                if now.wrapping_sub(since) > timeout {
+                    self.stalled_since = None;
+                    return nb::Error::Other(Error::FlowControl);
+                }
+            }
+            _ => {
+                self.stalled_since = Some(now);
+                self.free = free;
+            }
+        }
+
+        nb::Error::WouldBlock
+    }
+
+    fn progressed(&mut self) {
+        self.stalled_since = None;
+    }
+}
+
+/// Clock gate of the UART instance behind `uart`
+fn clock_gate(uart: &pac::uart0::RegisterBlock) -> Peripheral {
+    if core::ptr::eq(uart, pac::UART0::ptr()) {
+        Peripheral::Uart0
+    } else {
+        Peripheral::Uart1
+    }
+}
+
+/// Computes the bit period divisor of `baudrate` from the UART clock
+fn bit_period(uart_clk: Hertz, baudrate: Baud) -> Result<u16, ClockError> {
+    let baud = baudrate.0;
+    let unreachable = ClockError::UnreachableDriverClk {
+        requested: baud,
+        source: uart_clk.0,
+    };
+    // Can't possibly have a baudrate greater than uart_clock
+    if baud > uart_clk.0 {
+        return Err(unreachable);
+    }
+    // If we did this calculation using integer math, it always rounds down
+    // Reduce error by doing calculation using floating point, then
+    // add half before converting back to integer to round nearest instead
+    let ans_f = uart_clk.0 as f32 / baud as f32;
+    let ans = (ans_f + 0.5) as u32;
+
+    if !(1..=65535).contains(&ans) {
+        return Err(unreachable);
+    }
+
+    Ok(ans as u16)
 }
 
 impl<UART, PINS> Serial<UART, PINS>
@@ -166,27 +354,113 @@ where
     UART: Deref<Target = pac::uart0::RegisterBlock>,
     PINS: Pins<UART>,
 {
-    pub fn new(uart: UART, config: Config, pins: PINS, clocks: Clocks) -> Self {
-        // Initialize clocks and baudrate
-        let uart_clk = clocks.uart_clk();
-        let baud = config.baudrate.0;
-        let divisor = {
-            // Can't possibly have a baudrate greater than uart_clock
-            if baud > uart_clk.0 {
-                panic!("impossible baudrate");
-            }
-            // If we did this calculation using integer math, it always rounds down
-            // Reduce error by doing calculation using floating point, then
-            // add half before converting back to integer to round nearest instead
-            let ans_f = uart_clk.0 as f32 / baud as f32;
-            let ans = (ans_f + 0.5) as u32;
-
-            if !(1..=65535).contains(&ans) {
-                panic!("impossible baudrate");
-            }
+    /// Configures the UART, the settings are checked before anything is written
+    pub fn new(
+        uart: UART,
+        config: Config,
+        pins: PINS,
+        clocks: Clocks,
+    ) -> Result<Self, ConfigError> {
+        // The de-glitch cycle count is a 4 bit field
+        if config.rx_deglitch > 15 {
+            return Err(ConfigError::RxDeglitch(config.rx_deglitch));
+        }
+        let bit_period =
+            bit_period(clocks.uart_clk(), config.baudrate).map_err(ConfigError::Baudrate)?;
+
+        set_peripheral_clock(clock_gate(&uart), true);
 
-            ans as u16
+        let serial = Serial {
+            uart,
+            pins,
+            config,
+            clocks,
+            listening: 0,
+            cts: CtsWatch::new(PINS::HAS_CTS, &config, &clocks),
+            bit_period,
         };
+        serial.configure();
+
+        Ok(serial)
+    }
+
+    /// Pulses the UART software reset and re-applies the configuration
+    ///
+    /// Use this to recover a wedged UART; the FIFOs are emptied by the reset.
+    /// The events enabled with `listen` are enabled again.
+    pub fn reset(&mut self) {
+        reset_peripheral(clock_gate(&self.uart));
+        self.configure();
+        self.unmask_events(self.listening);
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        self.listening |= event.mask();
+        self.unmask_events(event.mask());
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        self.listening &= !event.mask();
+        self.uart
+            .uart_int_mask
+            .modify(|r, w| unsafe { w.bits(r.bits() | event.mask()) });
+        self.uart
+            .uart_int_en
+            .modify(|r, w| unsafe { w.bits(r.bits() & !event.mask()) });
+    }
+
+    /// Unmasks and enables the interrupt events in `mask`
+    fn unmask_events(&self, mask: u32) {
+        self.uart
+            .uart_int_mask
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask) });
+        self.uart
+            .uart_int_en
+            .modify(|r, w| unsafe { w.bits(r.bits() | mask) });
+    }
+
+    /// Checks whether an interrupt event is pending
+    pub fn is_pending(&self, event: Event) -> bool {
+        self.uart.uart_int_sts.read().bits() & event.mask() != 0
+    }
+
+    /// Clears a pending interrupt event
+    ///
+    /// The FIFO ready events clear themselves once the FIFO level is no longer past its
+    /// threshold. The FIFO error events are cleared by emptying the FIFO, which drops its content.
+    pub fn clear(&mut self, event: Event) {
+        match event {
+            Event::TxFifoReady | Event::RxFifoReady => {}
+            Event::TxFifoError => self
+                .uart
+                .uart_fifo_config_0
+                .modify(|_, w| w.tx_fifo_clr().set_bit()),
+            Event::RxFifoError => self
+                .uart
+                .uart_fifo_config_0
+                .modify(|_, w| w.rx_fifo_clr().set_bit()),
+            _ => self
+                .uart
+                .uart_int_clear
+                .write(|w| unsafe { w.bits(event.mask()) }),
+        }
+    }
+
+    pub fn free(self) -> (UART, PINS) {
+        set_peripheral_clock(clock_gate(&self.uart), false);
+
+        (self.uart, self.pins)
+    }
+
+    /// Writes the stored configuration to the UART registers, it has been checked by `new`
+    fn configure(&self) {
+        let uart = &self.uart;
+        let config = &self.config;
+
+        // Initialize clocks and baudrate
+        let divisor = self.bit_period;
 
         uart.uart_bit_prd.write(|w| unsafe {
             w.cr_urx_bit_prd()
@@ -233,7 +507,7 @@ where
                 .cr_utx_bit_cnt_p()
                 .bits(stop_bits_cfg)
                 .cr_utx_frm_en()
-                .set_bit() // [!] freerun on // todo
+                .set_bit() // freerun, the driver doesn't program transfer lengths
                 .cr_utx_cts_en()
                 .bit(PINS::HAS_CTS)
                 .cr_utx_en()
@@ -249,19 +523,53 @@ where
                 .cr_urx_bit_cnt_d()
                 .bits(data_bits_cfg)
                 .cr_urx_deg_en()
-                .clear_bit() // no rx input de-glitch // todo
+                .bit(config.rx_deglitch != 0)
+                .cr_urx_deg_cnt()
+                .bits(config.rx_deglitch)
                 .cr_urx_rts_sw_mode()
                 .clear_bit() // no RTS // todo
                 .cr_urx_en()
                 .bit(PINS::HAS_RX)
         });
+    }
+}
 
-        Serial { uart, pins }
+impl<UART, PINS> ClockDependent for Serial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+        bit_period(clocks.uart_clk(), self.config.baudrate).map(|_| ())
     }
 
-    pub fn free(self) -> (UART, PINS) {
-        // todo!
-        (self.uart, self.pins)
+    fn suspend(&mut self) -> Result<(), ClockError> {
+        // Let the TX FIFO drain so no byte is sent with a wrong bit period. Without a TX pin
+        // the transmitter is off and nothing drains it, with CTS the wait ends after
+        // `cts_timeout`
+        if PINS::HAS_TX {
+            block!(embedded_hal_nb::serial::Write::flush(self))
+                .map_err(|_| ClockError::DriverBusy)?;
+        }
+
+        self.uart
+            .utx_config
+            .modify(|_, w| w.cr_utx_en().clear_bit());
+        self.uart
+            .urx_config
+            .modify(|_, w| w.cr_urx_en().clear_bit());
+
+        Ok(())
+    }
+
+    fn resume(&mut self, clocks: &Clocks) {
+        // `check` accepted the new clocks, the old ones are passed back if the switch failed
+        if let Ok(bit_period) = bit_period(clocks.uart_clk(), self.config.baudrate) {
+            self.bit_period = bit_period;
+        }
+        self.clocks = *clocks;
+        self.cts = CtsWatch::new(PINS::HAS_CTS, &self.config, clocks);
+        self.configure();
     }
 }
 
@@ -274,26 +582,11 @@ where
     UART: Deref<Target = pac::uart0::RegisterBlock>,
 {
     fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
-        // If there's no room to write a byte or more to the FIFO, return WouldBlock
-        if self.uart.uart_fifo_config_1.read().tx_fifo_cnt().bits() == 0 {
-            Err(nb::Error::WouldBlock)
-        } else {
-            self.uart
-                .uart_fifo_wdata
-                .write(|w| unsafe { w.bits(word as u32) });
-            Ok(())
-        }
+        write_word(&self.uart, word, &mut self.cts)
     }
 
     fn flush(&mut self) -> nb::Result<(), Self::Error> {
-        // If we're still transmitting or have data in our 32 byte FIFO, return WouldBlock
-        if self.uart.uart_fifo_config_1.read().tx_fifo_cnt().bits() != 32
-            || self.uart.uart_status.read().sts_utx_bus_busy().bit_is_set()
-        {
-            Err(nb::Error::WouldBlock)
-        } else {
-            Ok(())
-        }
+        flush_tx(&self.uart, &mut self.cts)
     }
 }
 
@@ -302,13 +595,68 @@ where
     UART: Deref<Target = pac::uart0::RegisterBlock>,
 {
     fn read(&mut self) -> nb::Result<u8, Self::Error> {
-        if self.uart.uart_fifo_config_1.read().rx_fifo_cnt().bits() == 0 {
-            Err(nb::Error::WouldBlock)
-        } else {
-            let ans = self.uart.uart_fifo_rdata.read().bits();
-            Ok((ans & 0xff) as u8)
+        read_word(&self.uart)
+    }
+}
+
+fn write_word(
+    uart: &pac::uart0::RegisterBlock,
+    word: u8,
+    cts: &mut CtsWatch,
+) -> nb::Result<(), Error> {
+    // If there's no room to write a byte or more to the FIFO, return WouldBlock
+    if uart.uart_fifo_config_1.read().tx_fifo_cnt().bits() == 0 {
+        Err(cts.stalled(0))
+    } else {
+        cts.progressed();
+        uart.uart_fifo_wdata
+            .write(|w| unsafe { w.bits(word as u32) });
+        Ok(())
+    }
+}
+
+fn flush_tx(uart: &pac::uart0::RegisterBlock, cts: &mut CtsWatch) -> nb::Result<(), Error> {
+    // If we're still transmitting or have data in our 32 byte FIFO, return WouldBlock
+    let free = uart.uart_fifo_config_1.read().tx_fifo_cnt().bits();
+    if free != 32 {
+        Err(cts.stalled(free))
+    } else if uart.uart_status.read().sts_utx_bus_busy().bit_is_set() {
+        // The last byte is on the wire, CTS is only sampled before a byte starts
+        Err(nb::Error::WouldBlock)
+    } else {
+        cts.progressed();
+        Ok(())
+    }
+}
+
+fn read_word(uart: &pac::uart0::RegisterBlock) -> nb::Result<u8, Error> {
+    // The parity flag is sticky and isn't tied to a FIFO position, so it's reported as soon
+    // as it's seen and no byte is dropped for it
+    if uart.uart_int_sts.read().bits() & Event::RxParityError.mask() != 0 {
+        uart.uart_int_clear
+            .write(|w| unsafe { w.bits(Event::RxParityError.mask()) });
+        return Err(nb::Error::Other(Error::Parity));
+    }
+
+    if uart.uart_fifo_config_1.read().rx_fifo_cnt().bits() == 0 {
+        // The bytes lost to an overflow came after the ones still in the FIFO, so it's
+        // reported once the FIFO is empty. Clearing the FIFO is the only way to reset
+        // the sticky flag, with the FIFO empty nothing else is lost.
+        if uart
+            .uart_fifo_config_0
+            .read()
+            .rx_fifo_overflow()
+            .bit_is_set()
+        {
+            uart.uart_fifo_config_0
+                .modify(|_, w| w.rx_fifo_clr().set_bit());
+            return Err(nb::Error::Other(Error::Overrun));
         }
+
+        return Err(nb::Error::WouldBlock);
     }
+
+    Ok((uart.uart_fifo_rdata.read().bits() & 0xff) as u8)
 }
 
 impl<UART, PINS> embedded_hal_zero::serial::Write<u8> for Serial<UART, PINS>
@@ -349,6 +697,533 @@ where
     }
 }
 
+/// UART peripheral whose registers can be reached without the peripheral value
+pub trait Instance: Deref<Target = pac::uart0::RegisterBlock> + Sealed {
+    #[doc(hidden)]
+    fn ptr() -> *const pac::uart0::RegisterBlock;
+}
+
+impl Instance for pac::UART0 {
+    fn ptr() -> *const pac::uart0::RegisterBlock {
+        pac::UART0::ptr()
+    }
+}
+
+impl Instance for pac::UART1 {
+    fn ptr() -> *const pac::uart0::RegisterBlock {
+        pac::UART1::ptr()
+    }
+}
+
+/// Transmitting half of a split [`Serial`]
+pub struct Tx<UART> {
+    cts: CtsWatch,
+    _uart: PhantomData<UART>,
+}
+
+/// Receiving half of a split [`Serial`]
+pub struct Rx<UART> {
+    _uart: PhantomData<UART>,
+}
+
+/// UART, pins and settings of a split [`Serial`], kept until the halves are reunited
+///
+/// The halves only carry the UART type, so the pins stay here instead of in one of them.
+pub struct Detached<UART, PINS> {
+    serial: Serial<UART, PINS>,
+}
+
+impl<UART, PINS> Serial<UART, PINS>
+where
+    UART: Instance,
+    PINS: Duplex<UART>,
+{
+    /// Splits the serial into halves which can be used independently
+    ///
+    /// Only serials with both a TX and an RX pin can be split.
+    pub fn split(self) -> (Tx<UART>, Rx<UART>, Detached<UART, PINS>) {
+        (
+            Tx {
+                cts: self.cts,
+                _uart: PhantomData,
+            },
+            Rx { _uart: PhantomData },
+            Detached { serial: self },
+        )
+    }
+}
+
+impl<UART, PINS> Detached<UART, PINS>
+where
+    UART: Instance,
+    PINS: Pins<UART>,
+{
+    /// Puts the halves back together
+    pub fn reunite(self, _tx: Tx<UART>, _rx: Rx<UART>) -> Serial<UART, PINS> {
+        self.serial
+    }
+
+    /// Releases the UART peripheral and the pins, see `Serial::free`
+    pub fn release(self, tx: Tx<UART>, rx: Rx<UART>) -> (UART, PINS) {
+        self.reunite(tx, rx).free()
+    }
+}
+
+impl<UART> embedded_hal_nb::serial::ErrorType for Tx<UART> {
+    type Error = Error;
+}
+
+impl<UART: Instance> embedded_hal_nb::serial::Write for Tx<UART> {
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        write_word(unsafe { &*UART::ptr() }, word, &mut self.cts)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        flush_tx(unsafe { &*UART::ptr() }, &mut self.cts)
+    }
+}
+
+impl<UART: Instance> embedded_hal_zero::serial::Write<u8> for Tx<UART> {
+    type Error = Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        write_word(unsafe { &*UART::ptr() }, word, &mut self.cts)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        flush_tx(unsafe { &*UART::ptr() }, &mut self.cts)
+    }
+}
+
+impl<UART> fmt::Write for Tx<UART>
+where
+    Tx<UART>: embedded_hal_nb::serial::Write<u8>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.as_bytes()
+            .iter()
+            .try_for_each(|c| block!(self.write(*c)))
+            .map_err(|_| fmt::Error)
+    }
+}
+
+impl<UART> embedded_hal_nb::serial::ErrorType for Rx<UART> {
+    type Error = Error;
+}
+
+impl<UART: Instance> embedded_hal_nb::serial::Read<u8> for Rx<UART> {
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        read_word(unsafe { &*UART::ptr() })
+    }
+}
+
+impl<UART: Instance> embedded_hal_zero::serial::Read<u8> for Rx<UART> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        read_word(unsafe { &*UART::ptr() })
+    }
+}
+
+/// Ring buffer for a [`BufferedSerial`], shared with the UART interrupt
+///
+/// Declare it as a `static` and hand it out with `Serial::into_buffered`:
+/// ```rust
+/// static RX: RingBuffer<256> = RingBuffer::new();
+/// static TX: RingBuffer<64> = RingBuffer::new();
+///
+/// let mut serial = serial.into_buffered(&RX, &TX);
+/// ```
+pub struct RingBuffer<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    // Both run over 0..2N so a full buffer can be told apart from an empty one
+    read: AtomicUsize,
+    write: AtomicUsize,
+    overflows: AtomicUsize,
+    parity: AtomicBool,
+    in_use: AtomicBool,
+}
+
+// Only one side pushes and only the other side pops, see `Ring`
+unsafe impl<const N: usize> Sync for RingBuffer<N> {}
+
+impl<const N: usize> RingBuffer<N> {
+    /// Creates an empty ring buffer
+    pub const fn new() -> Self {
+        RingBuffer {
+            buf: UnsafeCell::new([0; N]),
+            read: AtomicUsize::new(0),
+            write: AtomicUsize::new(0),
+            overflows: AtomicUsize::new(0),
+            parity: AtomicBool::new(false),
+            in_use: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Single producer, single consumer byte queue, independent of the buffer size
+trait Ring: Sync {
+    fn push(&self, byte: u8) -> bool;
+    fn pop(&self) -> Option<u8>;
+    fn is_empty(&self) -> bool;
+    fn count_overflow(&self);
+    fn overflows(&self) -> usize;
+    fn latch_parity(&self);
+    fn take_parity(&self) -> bool;
+    fn acquire(&self);
+    fn release(&self);
+}
+
+impl<const N: usize> Ring for RingBuffer<N> {
+    fn push(&self, byte: u8) -> bool {
+        let write = self.write.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        if (write + 2 * N - read) % (2 * N) == N {
+            return false;
+        }
+
+        unsafe { (*self.buf.get())[write % N] = byte };
+        self.write.store((write + 1) % (2 * N), Ordering::Release);
+
+        true
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let read = self.read.load(Ordering::Relaxed);
+        let write = self.write.load(Ordering::Acquire);
+        if read == write {
+            return None;
+        }
+
+        let byte = unsafe { (*self.buf.get())[read % N] };
+        self.read.store((read + 1) % (2 * N), Ordering::Release);
+
+        Some(byte)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.read.load(Ordering::Acquire) == self.write.load(Ordering::Acquire)
+    }
+
+    fn count_overflow(&self) {
+        self.overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn overflows(&self) -> usize {
+        self.overflows.load(Ordering::Relaxed)
+    }
+
+    fn latch_parity(&self) {
+        self.parity.store(true, Ordering::Release);
+    }
+
+    fn take_parity(&self) -> bool {
+        self.parity.swap(false, Ordering::AcqRel)
+    }
+
+    fn acquire(&self) {
+        if self.in_use.swap(true, Ordering::AcqRel) {
+            panic!("ring buffer already in use");
+        }
+        self.read.store(0, Ordering::Relaxed);
+        self.write.store(0, Ordering::Relaxed);
+        self.parity.store(false, Ordering::Relaxed);
+    }
+
+    fn release(&self) {
+        self.in_use.store(false, Ordering::Release);
+    }
+}
+
+/// RX and TX ring buffers of a buffered UART
+type Buffers = Option<(&'static dyn Ring, &'static dyn Ring)>;
+
+/// Ring buffers of the buffered UARTs, indexed like `uart_index`
+static BUFFERS: [Mutex<Cell<Buffers>>; 2] =
+    [Mutex::new(Cell::new(None)), Mutex::new(Cell::new(None))];
+static FIFO_OVERRUNS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+
+fn uart_index(uart: &pac::uart0::RegisterBlock) -> usize {
+    match clock_gate(uart) {
+        Peripheral::Uart0 => 0,
+        _ => 1,
+    }
+}
+
+fn uart_interrupt(index: usize) -> Interrupt {
+    if index == 0 {
+        Interrupt::Uart0
+    } else {
+        Interrupt::Uart1
+    }
+}
+
+/// Moves bytes between the FIFOs and the ring buffers of UART `index`
+///
+/// Called from the UART interrupt, returns whether the UART is buffered.
+pub(crate) fn on_buffered_interrupt(index: usize) -> bool {
+    let (rx, tx) = match critical_section::with(|cs| BUFFERS[index].borrow(cs).get()) {
+        Some(buffers) => buffers,
+        None => return false,
+    };
+    let uart = unsafe {
+        if index == 0 {
+            &*pac::UART0::ptr()
+        } else {
+            &*pac::UART1::ptr()
+        }
+    };
+
+    drain_rx(uart, rx);
+
+    if uart
+        .uart_fifo_config_0
+        .read()
+        .rx_fifo_overflow()
+        .bit_is_set()
+    {
+        FIFO_OVERRUNS[index].fetch_add(1, Ordering::Relaxed);
+        // Clearing the FIFO is the only way to reset the overflow flag,
+        // pick up what arrived since the drain right after
+        uart.uart_fifo_config_0
+            .modify(|_, w| w.rx_fifo_clr().set_bit());
+        drain_rx(uart, rx);
+    }
+
+    let status = uart.uart_int_sts.read().bits();
+    if status & Event::RxTimeout.mask() != 0 {
+        uart.uart_int_clear
+            .write(|w| unsafe { w.bits(Event::RxTimeout.mask()) });
+    }
+    // Like in the polled path the sticky flag isn't tied to a byte, `read` reports it once
+    if status & Event::RxParityError.mask() != 0 {
+        uart.uart_int_clear
+            .write(|w| unsafe { w.bits(Event::RxParityError.mask()) });
+        rx.latch_parity();
+    }
+
+    while uart.uart_fifo_config_1.read().tx_fifo_cnt().bits() > 0 {
+        match tx.pop() {
+            Some(byte) => uart
+                .uart_fifo_wdata
+                .write(|w| unsafe { w.bits(byte as u32) }),
+            None => {
+                // Nothing left to send, stop the FIFO ready interrupt until the next write
+                uart.uart_int_mask
+                    .modify(|r, w| unsafe { w.bits(r.bits() | Event::TxFifoReady.mask()) });
+                break;
+            }
+        }
+    }
+
+    clear_interrupt(uart_interrupt(index));
+
+    true
+}
+
+/// Moves the content of the RX FIFO into the `rx` ring buffer
+fn drain_rx(uart: &pac::uart0::RegisterBlock, rx: &dyn Ring) {
+    while uart.uart_fifo_config_1.read().rx_fifo_cnt().bits() > 0 {
+        let byte = (uart.uart_fifo_rdata.read().bits() & 0xff) as u8;
+        if !rx.push(byte) {
+            rx.count_overflow();
+        }
+    }
+}
+
+/// Interrupt driven serial, moving data through ring buffers
+///
+/// The UART interrupt is handled by the HAL while the serial is buffered, the `Uart0`/`Uart1`
+/// handler isn't called. Bytes received while the RX ring buffer is full are dropped and
+/// counted, see `rx_overflows`. A parity error is reported once by the next `read`, the
+/// received bytes are kept.
+pub struct BufferedSerial<UART, PINS> {
+    serial: Serial<UART, PINS>,
+    rx: &'static dyn Ring,
+    tx: &'static dyn Ring,
+}
+
+impl<UART, PINS> Serial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    /// Moves the serial to interrupt driven operation on the `rx` and `tx` ring buffers
+    ///
+    /// Panics if one of the buffers is used by another `BufferedSerial`.
+    pub fn into_buffered<const RX: usize, const TX: usize>(
+        mut self,
+        rx: &'static RingBuffer<RX>,
+        tx: &'static RingBuffer<TX>,
+    ) -> BufferedSerial<UART, PINS> {
+        let (rx, tx): (&'static dyn Ring, &'static dyn Ring) = (rx, tx);
+        rx.acquire();
+        tx.acquire();
+
+        let index = uart_index(&self.uart);
+        disable_interrupt(uart_interrupt(index));
+        critical_section::with(|cs| BUFFERS[index].borrow(cs).set(Some((rx, tx))));
+
+        // Interrupt once the RX FIFO holds more than 16 bytes or the line stays idle
+        // for 8 frames, and while the TX FIFO has room for more than 16 bytes
+        self.uart
+            .uart_fifo_config_1
+            .modify(|_, w| unsafe { w.rx_fifo_th().bits(15).tx_fifo_th().bits(15) });
+        self.uart
+            .urx_rto_timer
+            .write(|w| unsafe { w.cr_urx_rto_value().bits(80) });
+
+        self.listen(Event::RxFifoReady);
+        self.listen(Event::RxTimeout);
+        self.listen(Event::RxFifoError);
+        enable_interrupt(uart_interrupt(index));
+
+        BufferedSerial {
+            serial: self,
+            rx,
+            tx,
+        }
+    }
+}
+
+impl<UART, PINS> BufferedSerial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    /// Number of received bytes dropped because the RX ring buffer was full
+    pub fn rx_overflows(&self) -> usize {
+        self.rx.overflows()
+    }
+
+    /// Number of times the RX FIFO overran before the interrupt emptied it
+    pub fn fifo_overruns(&self) -> usize {
+        FIFO_OVERRUNS[uart_index(&self.serial.uart)].load(Ordering::Relaxed)
+    }
+
+    /// Waits for the queued data to be sent and goes back to polled operation
+    pub fn release(mut self) -> Serial<UART, PINS> {
+        embedded_io::Write::flush(&mut self).ok();
+
+        let index = uart_index(&self.serial.uart);
+        disable_interrupt(uart_interrupt(index));
+        for event in [
+            Event::RxFifoReady,
+            Event::RxTimeout,
+            Event::RxFifoError,
+            Event::TxFifoReady,
+        ] {
+            self.serial.unlisten(event);
+        }
+        critical_section::with(|cs| BUFFERS[index].borrow(cs).set(None));
+
+        self.rx.release();
+        self.tx.release();
+
+        self.serial
+    }
+}
+
+impl<UART, PINS> ClockDependent for BufferedSerial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+        self.serial.check(clocks)
+    }
+
+    /// Pauses the interrupt and empties the FIFOs, queued bytes are sent after `resume`
+    fn suspend(&mut self) -> Result<(), ClockError> {
+        let index = uart_index(&self.serial.uart);
+        disable_interrupt(uart_interrupt(index));
+        drain_rx(&self.serial.uart, self.rx);
+
+        self.serial.suspend()
+    }
+
+    fn resume(&mut self, clocks: &Clocks) {
+        self.serial.resume(clocks);
+
+        enable_interrupt(uart_interrupt(uart_index(&self.serial.uart)));
+    }
+}
+
+impl<UART, PINS> embedded_io::ErrorType for BufferedSerial<UART, PINS> {
+    type Error = Error;
+}
+
+impl<UART, PINS> embedded_io::Read for BufferedSerial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    /// Waits for at least one byte and reads what is buffered
+    ///
+    /// Returns `Error::Parity` once if the interrupt saw a parity error since the last call.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.rx.take_parity() {
+                return Err(Error::Parity);
+            }
+            if !self.rx.is_empty() {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+
+        let mut count = 0;
+        for slot in buf.iter_mut() {
+            match self.rx.pop() {
+                Some(byte) => *slot = byte,
+                None => break,
+            }
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+impl<UART, PINS> embedded_io::Write for BufferedSerial<UART, PINS>
+where
+    UART: Deref<Target = pac::uart0::RegisterBlock>,
+    PINS: Pins<UART>,
+{
+    /// Waits for room for at least one byte and queues what fits
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        while count == 0 {
+            count = buf.iter().take_while(|byte| self.tx.push(**byte)).count();
+        }
+
+        self.serial.listen(Event::TxFifoReady);
+
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        while !self.tx.is_empty() {
+            core::hint::spin_loop();
+        }
+        block!(embedded_hal_nb::serial::Write::flush(&mut self.serial))
+    }
+}
+
 /// Serial transmit pins
 pub trait TxPin<UART>: Sealed {}
 /// Serial receive pins
@@ -387,6 +1262,21 @@ impl_uart_pin!(
 );
 
 /// Serial pins - DO NOT IMPLEMENT THIS TRAIT
+///
+/// Each signal is a pair of a pin in UART mode and the multiplexer of the pin's UART signal,
+/// set to that signal. `Pin16` carries UART signal 0 for example, so UART0 TX on it is
+/// `(Pin16<Uart>, UartMux0<Uart0Tx>)`; pairing it with another multiplexer or setting
+/// for a different UART doesn't compile.
+///
+/// ```rust
+/// let tx = parts.pin16.into_uart_sig0();
+/// let rx = parts.pin7.into_uart_sig7();
+/// let mux0 = parts.uart_mux0.into_uart0_tx();
+/// let mux7 = parts.uart_mux7.into_uart0_rx();
+///
+/// let serial =
+///     Serial::new(dp.UART0, Config::default(), ((tx, mux0), (rx, mux7)), clocks).unwrap();
+/// ```
 pub trait Pins<UART>: Sealed {
     const HAS_TX: bool;
     const HAS_RX: bool;
@@ -405,12 +1295,57 @@ where
     const HAS_CTS: bool = false;
 }
 
+/// Serial pins with both a TX and an RX signal, which can be split into [`Tx`] and [`Rx`]
+pub trait Duplex<UART>: Pins<UART> {}
+
+impl<UART, TX, RX> Duplex<UART> for (TX, RX)
+where
+    TX: TxPin<UART>,
+    RX: RxPin<UART>,
+{
+}
+
+impl<UART, TX, RX, RTS, CTS> Duplex<UART> for (TX, RX, RTS, CTS)
+where
+    TX: TxPin<UART>,
+    RX: RxPin<UART>,
+    RTS: RtsPin<UART>,
+    CTS: CtsPin<UART>,
+{
+}
+
+/// Placeholder for the missing TX signal of a receive-only serial, `(NoTx, rx)`
+pub struct NoTx;
+
+/// Placeholder for the missing RX signal of a transmit-only serial, `(tx, NoRx)`
+pub struct NoRx;
+
+impl<UART, TX> Pins<UART> for (TX, NoRx)
+where
+    TX: TxPin<UART>,
+{
+    const HAS_TX: bool = true;
+    const HAS_RX: bool = false;
+    const HAS_RTS: bool = false;
+    const HAS_CTS: bool = false;
+}
+
+impl<UART, RX> Pins<UART> for (NoTx, RX)
+where
+    RX: RxPin<UART>,
+{
+    const HAS_TX: bool = false;
+    const HAS_RX: bool = true;
+    const HAS_RTS: bool = false;
+    const HAS_CTS: bool = false;
+}
+
 impl<UART, TX, RX, RTS, CTS> Pins<UART> for (TX, RX, RTS, CTS)
 where
     TX: TxPin<UART>,
     RX: RxPin<UART>,
-    RTS: RxPin<UART>,
-    CTS: RxPin<UART>,
+    RTS: RtsPin<UART>,
+    CTS: CtsPin<UART>,
 {
     const HAS_TX: bool = true;
     const HAS_RX: bool = true;
@@ -423,6 +1358,8 @@ mod private {
     use crate::gpio;
 
     pub trait Sealed {}
+    impl Sealed for crate::pac::UART0 {}
+    impl Sealed for crate::pac::UART1 {}
     impl<TX, RX> Sealed for (TX, RX) {}
     impl<TX, RX, RTS, CTS> Sealed for (TX, RX, RTS, CTS) {}
 
@@ -29,7 +29,9 @@ use embedded_time::rate::Hertz;
 
 use crate::pac;
 
-use crate::clock::Clocks;
+use crate::clock::{
+    reset_peripheral, set_peripheral_clock, ClockDependent, ClockError, Clocks, Peripheral,
+};
 
 /// SPI error
 #[derive(Debug)]
@@ -176,6 +178,46 @@ mod private {
 pub struct Spi<SPI, PINS> {
     spi: SPI,
     pins: PINS,
+    mode: Mode,
+    freq: Hertz<u32>,
+    bit_format: SpiBitFormat,
+    clocks: Clocks,
+}
+
+/// Computes the register value of the SCLK phase lengths for `freq` derived from the SPI clock
+fn phase_len(freq: Hertz<u32>, clocks: &Clocks) -> Result<u8, ClockError> {
+    // length of phase 0 and 1 (i.e. low / high values of SCLK)
+    // needs to be divided by two
+    let len = clocks.spi_clk().0 / freq.0 / 2;
+    if len > 256 || len == 0 {
+        return Err(ClockError::UnreachableDriverClk {
+            requested: freq.0,
+            source: clocks.spi_clk().0,
+        });
+    }
+
+    Ok((len - 1) as u8)
+}
+
+/// Writes the SCLK phase lengths for `freq` derived from the SPI clock
+fn set_frequency(spi: &pac::SPI, freq: Hertz<u32>, clocks: &Clocks) {
+    let len = match phase_len(freq, clocks) {
+        Ok(len) => len,
+        Err(_) => panic!("Cannot reach the desired SPI frequency"),
+    };
+    spi.spi_prd_0.modify(|_r, w| unsafe {
+        w.cr_spi_prd_s()
+            .bits(len)
+            .cr_spi_prd_p()
+            .bits(len)
+            .cr_spi_prd_d_ph_0()
+            .bits(len)
+            .cr_spi_prd_d_ph_1()
+            .bits(len)
+    });
+
+    spi.spi_prd_1
+        .modify(|_r, w| unsafe { w.cr_spi_prd_i().bits(len) });
 }
 
 impl<PINS> Spi<pac::SPI, PINS>
@@ -193,6 +235,37 @@ where
     where
         PINS: Pins<pac::SPI>,
     {
+        set_peripheral_clock(Peripheral::Spi, true);
+
+        let spi = Spi {
+            spi,
+            pins,
+            mode,
+            freq,
+            bit_format: SpiBitFormat::MsbFirst,
+            clocks,
+        };
+        spi.configure();
+
+        spi
+    }
+
+    pub fn release(self) -> (pac::SPI, PINS) {
+        set_peripheral_clock(Peripheral::Spi, false);
+
+        (self.spi, self.pins)
+    }
+
+    /// Pulses the SPI software reset and re-applies the configuration
+    ///
+    /// Use this to recover a wedged SPI; the FIFOs are emptied by the reset.
+    pub fn reset(&mut self) {
+        reset_peripheral(Peripheral::Spi);
+        self.configure();
+    }
+
+    /// Writes the stored configuration to the SPI registers
+    fn configure(&self) {
         let glb = unsafe { &*pac::GLB::ptr() };
 
         glb.glb_parm.modify(|_r, w| {
@@ -202,29 +275,10 @@ where
                 .set_bit()
         });
 
-        // length of phase 0 and 1 (i.e. low / high values of SCLK)
-        // needs to be divided by two
-        let len = clocks.spi_clk().0 / freq.0 / 2;
-        if len > 256 || len == 0 {
-            panic!("Cannot reach the desired SPI frequency");
-        }
-
-        let len = (len - 1) as u8;
-        spi.spi_prd_0.modify(|_r, w| unsafe {
-            w.cr_spi_prd_s()
-                .bits(len)
-                .cr_spi_prd_p()
-                .bits(len)
-                .cr_spi_prd_d_ph_0()
-                .bits(len)
-                .cr_spi_prd_d_ph_1()
-                .bits(len)
-        });
-
-        spi.spi_prd_1
-            .modify(|_r, w| unsafe { w.cr_spi_prd_i().bits(len) });
+        set_frequency(&self.spi, self.freq, &self.clocks);
 
-        spi.spi_config.modify(|_, w| unsafe {
+        let mode = self.mode;
+        self.spi.spi_config.modify(|_, w| unsafe {
             w.cr_spi_sclk_pol()
                 .bit(match mode.polarity {
                     embedded_hal::spi::Polarity::IdleLow => false,
@@ -239,21 +293,19 @@ where
                 .clear_bit() // disable cont mode
                 .cr_spi_frame_size()
                 .bits(0) // 8 bit frames
+                .cr_spi_bit_inv()
+                .bit(matches!(self.bit_format, SpiBitFormat::LsbFirst))
                 .cr_spi_s_en()
                 .clear_bit() // not slave
                 .cr_spi_m_en()
                 .set_bit() // master
         });
-
-        Spi { spi, pins }
-    }
-
-    pub fn release(self) -> (pac::SPI, PINS) {
-        (self.spi, self.pins)
     }
 
     /// Select which frame format is used for data transfers
     pub fn bit_format(&mut self, format: SpiBitFormat) {
+        self.bit_format = format;
+
         match format {
             SpiBitFormat::LsbFirst => self
                 .spi
@@ -274,6 +326,33 @@ where
     }
 }
 
+impl<PINS> ClockDependent for Spi<pac::SPI, PINS>
+where
+    PINS: Pins<pac::SPI>,
+{
+    fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+        phase_len(self.freq, clocks).map(|_| ())
+    }
+
+    fn suspend(&mut self) -> Result<(), ClockError> {
+        // Wait for the current frame to be shifted out before stopping the master
+        while self.spi.spi_bus_busy.read().sts_spi_bus_busy().bit_is_set() {}
+
+        self.spi
+            .spi_config
+            .modify(|_, w| w.cr_spi_m_en().clear_bit());
+
+        Ok(())
+    }
+
+    fn resume(&mut self, clocks: &Clocks) {
+        self.clocks = *clocks;
+        set_frequency(&self.spi, self.freq, clocks);
+
+        self.spi.spi_config.modify(|_, w| w.cr_spi_m_en().set_bit());
+    }
+}
+
 impl<PINS> embedded_hal_nb::spi::ErrorType for Spi<pac::SPI, PINS> {
     type Error = Error;
 }
@@ -35,7 +35,10 @@
   ```
 */
 
-use crate::{clock::Clocks, pac};
+use crate::{
+    clock::{set_peripheral_clock, ClockDependent, ClockError, Clocks, Peripheral},
+    pac,
+};
 use bl602_pac::TIMER;
 use core::cell::RefCell;
 use embedded_time::{duration::*, rate::*};
@@ -82,6 +85,20 @@ impl<'a> ClockSource<'a> {
     }
 }
 
+/// Computes the `tcdr` value dividing `source` down to `target`
+fn clock_divider(source: Hertz, target: Hertz) -> Result<u8, ClockError> {
+    let divider = (source / target.0).0;
+
+    if !(1..=256).contains(&divider) {
+        return Err(ClockError::UnreachableDriverClk {
+            requested: target.0,
+            source: source.0,
+        });
+    }
+
+    Ok((divider - 1) as u8)
+}
+
 /// When to preload
 pub enum Preload {
     /// No preload
@@ -106,19 +123,38 @@ impl Preload {
 }
 
 /// Timer Channel 0
-pub struct TimerChannel0 {}
+pub struct TimerChannel0 {
+    _ownership: (),
+}
 
 /// Timer Channel 1
-pub struct TimerChannel1 {}
+pub struct TimerChannel1 {
+    _ownership: (),
+}
 
 /// Watchdog Timer
-pub struct TimerWatchdog {}
+pub struct TimerWatchdog {
+    pub(crate) _ownership: (),
+}
 
 /// Timers obtained from [TIMER.split](bl602_pac::Peripherals::TIMER)
 pub struct Timers {
     pub channel0: TimerChannel0,
     pub channel1: TimerChannel1,
     pub watchdog: TimerWatchdog,
+    timer: TIMER,
+}
+
+impl Timers {
+    /// Gates the timer peripheral clock off and gives the peripheral back
+    ///
+    /// Needs the channels and the watchdog back unconfigured, see `free` of the configured
+    /// channels and of `ConfiguredWatchdog0`.
+    pub fn free(self) -> TIMER {
+        set_peripheral_clock(Peripheral::Timer, false);
+
+        self.timer
+    }
 }
 
 macro_rules! impl_timer_channel {
@@ -127,9 +163,12 @@ macro_rules! impl_timer_channel {
         /// A configured timer channel ready to use.
         pub struct $conf_name {
             clock: Hertz,
+            fclk_source: bool,
             count_down_target: Option<Nanoseconds::<u64>>,
             last_count_down_value: Option<Nanoseconds::<u64>>,
             is_running: RefCell<bool>,
+            /// Stopped by `ClockDependent::suspend`, restarted by `resume`
+            suspended: bool,
         }
 
         paste! {
@@ -184,6 +223,13 @@ macro_rules! impl_timer_channel {
                     self.is_running.replace(false);
                 }
 
+                /// Stops the counter and gives the unconfigured channel back
+                pub fn free(self) -> $name {
+                    let timer = unsafe { &*pac::TIMER::ptr() };
+                    timer.tcer.modify(|_r, w| w.[<timer $channel _en>]().clear_bit());
+                    $name { _ownership: () }
+                }
+
                 /// Check if the timer is enabled / running
                 pub fn is_enabled(&self) -> bool {
                     *self.is_running.borrow()
@@ -295,6 +341,51 @@ macro_rules! impl_timer_channel {
             }
         }
 
+        paste! {
+            impl ClockDependent for $conf_name {
+                fn check(&self, clocks: &Clocks) -> Result<(), ClockError> {
+                    if !self.fclk_source {
+                        return Ok(());
+                    }
+
+                    clock_divider(clocks.sysclk(), self.clock).map(|_| ())
+                }
+
+                /// Stops the counter if it counts `Fclk` ticks, so no tick is counted at a wrong rate
+                fn suspend(&mut self) -> Result<(), ClockError> {
+                    if self.fclk_source && self.is_enabled() {
+                        let timer = unsafe { &*pac::TIMER::ptr() };
+                        timer.tcer.modify(|_r, w| w.[<timer $channel _en>]().clear_bit());
+                        self.suspended = true;
+                    }
+
+                    Ok(())
+                }
+
+                /// Recomputes the clock divider if the channel counts `Fclk` ticks and restarts
+                /// the counter stopped by `suspend`
+                fn resume(&mut self, clocks: &Clocks) {
+                    if !self.fclk_source {
+                        return;
+                    }
+
+                    let timer = unsafe { &*pac::TIMER::ptr() };
+
+                    // `check` accepted the new clocks, the old ones are passed back if the switch failed
+                    if let Ok(divider) = clock_divider(clocks.sysclk(), self.clock) {
+                        timer
+                            .tcdr
+                            .modify(|_r, w| unsafe { w.[<tcdr $channel>]().bits(divider) });
+                    }
+
+                    if self.suspended {
+                        self.suspended = false;
+                        timer.tcer.modify(|_r, w| w.[<timer $channel _en>]().set_bit());
+                    }
+                }
+            }
+        }
+
         impl embedded_hal_zero::timer::CountDown for $conf_name {
             type Time = Nanoseconds::<u64>;
 
@@ -348,15 +439,14 @@ macro_rules! impl_timer_channel {
                         .tccr
                         .modify(|_r, w| unsafe { w.[<cs_ $channel_cs>]().bits(source.tccr_value()) });
 
-                    let divider = (source.hertz() / target_clock.0).0;
-
-                    if !(1..=256).contains(&divider) {
-                        panic!("Unreachable target clock");
-                    }
+                    let divider = match clock_divider(source.hertz(), target_clock) {
+                        Ok(divider) => divider,
+                        Err(_) => panic!("Unreachable target clock"),
+                    };
 
                     timer
                         .tcdr
-                        .modify(|_r, w| unsafe { w.[<tcdr $channel>]().bits((divider - 1) as u8) });
+                        .modify(|_r, w| unsafe { w.[<tcdr $channel>]().bits(divider) });
 
                     timer.tcmr.modify(|_r, w| {
                         w.[<timer $channel _mode>]().clear_bit() // pre-load mode
@@ -367,9 +457,11 @@ macro_rules! impl_timer_channel {
 
                     $conf_name {
                         clock: target_clock,
+                        fclk_source: matches!(source, ClockSource::Fclk(_)),
                         count_down_target: None,
                         last_count_down_value: None,
                         is_running: RefCell::new(false),
+                        suspended: false,
                     }
                 }
             }
@@ -388,10 +480,13 @@ pub trait TimerExt {
 
 impl TimerExt for TIMER {
     fn split(self) -> Timers {
+        set_peripheral_clock(Peripheral::Timer, true);
+
         Timers {
-            channel0: TimerChannel0 {},
-            channel1: TimerChannel1 {},
-            watchdog: TimerWatchdog {},
+            channel0: TimerChannel0 { _ownership: () },
+            channel1: TimerChannel1 { _ownership: () },
+            watchdog: TimerWatchdog { _ownership: () },
+            timer: self,
         }
     }
 }
@@ -239,6 +239,14 @@ impl embedded_hal_zero::watchdog::Watchdog for ConfiguredWatchdog0 {
     }
 }
 
+impl ConfiguredWatchdog0 {
+    /// Disables the watchdog and gives the unconfigured watchdog back
+    pub fn free(mut self) -> TimerWatchdog {
+        embedded_hal_zero::watchdog::WatchdogDisable::disable(&mut self);
+        TimerWatchdog { _ownership: () }
+    }
+}
+
 impl embedded_hal_zero::watchdog::WatchdogDisable for ConfiguredWatchdog0 {
     fn disable(&mut self) {
         let timer = unsafe { &*pac::TIMER::ptr() };